@@ -0,0 +1,65 @@
+use nalgebra_glm::Vec3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    pub fn empty() -> Self {
+        Aabb {
+            min: Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    pub fn grow(&mut self, point: &Vec3) {
+        self.min = self.min.inf(point);
+        self.max = self.max.sup(point);
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let size = self.max - self.min;
+        if size.x < 0.0 || size.y < 0.0 || size.z < 0.0 {
+            return 0.0;
+        }
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    // Slab test; devuelve la distancia de entrada si el rayo toca la caja antes de `t_max`
+    pub fn hit(&self, ray_origin: &Vec3, inv_dir: &Vec3, t_max: f32) -> Option<f32> {
+        let tmin = (self.min - ray_origin).component_mul(inv_dir);
+        let tmax = (self.max - ray_origin).component_mul(inv_dir);
+
+        let t_enter = tmin[0]
+            .min(tmax[0])
+            .max(tmin[1].min(tmax[1]))
+            .max(tmin[2].min(tmax[2]));
+        let t_exit = tmin[0]
+            .max(tmax[0])
+            .min(tmin[1].max(tmax[1]))
+            .min(tmin[2].max(tmax[2]));
+
+        if t_enter <= t_exit && t_exit > 0.0 && t_enter < t_max {
+            Some(t_enter)
+        } else {
+            None
+        }
+    }
+}
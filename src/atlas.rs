@@ -0,0 +1,235 @@
+use crate::color::Color;
+use crate::texture::{decode_colors, Texture, TextureView, UvRect};
+use image::ImageReader;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Texels que se repiten alrededor de cada tile para que el muestreo cerca del borde no tome
+// colores del vecino; se redondea hacia arriba a la alineación
+const PADDING: usize = 2;
+
+// Los tiles empiezan en múltiplos de esto (o del mayor divisor de su tamaño que sea potencia de
+// dos, si es menor) para que cada mipmap del atlas promedie texels de un solo tile. 16 deja
+// llegar un tile de 16x16 hasta 1x1.
+const MAX_ALIGNMENT: usize = 16;
+
+// Los tiles con estos sufijos son datos (normales, rugosidad...) y se leen sin corrección sRGB
+const DATA_SUFFIXES: [&str; 3] = ["_normal", "_roughness", "_metallic"];
+
+#[derive(Debug)]
+pub struct AtlasError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl std::error::Error for AtlasError {}
+
+// Tiles de una carpeta empaquetados en dos atlas: uno de color y otro de datos. Cada tile se
+// pide por el nombre de su archivo sin extensión; los archivos con la misma imagen comparten
+// el mismo lugar en el atlas.
+#[derive(Debug, Clone)]
+pub struct TextureRegistry {
+    folder: PathBuf,
+    color: Arc<Texture>,
+    data: Arc<Texture>,
+    color_tiles: HashMap<String, UvRect>,
+    data_tiles: HashMap<String, UvRect>,
+    files: Vec<PathBuf>,
+}
+
+struct Image {
+    width: usize,
+    height: usize,
+    colors: Vec<Color>,
+}
+
+// Un atlas en construcción: imágenes únicas y, por cada nombre, cuál le toca
+#[derive(Default)]
+struct Packer {
+    images: Vec<Image>,
+    by_content: HashMap<(usize, usize, Vec<u8>), usize>,
+    names: Vec<(String, usize)>,
+}
+
+impl Packer {
+    fn add(&mut self, name: String, width: usize, height: usize, raw: Vec<u8>, colors: Vec<Color>) {
+        let next = self.images.len();
+        let index = *self.by_content.entry((width, height, raw)).or_insert(next);
+        if index == next {
+            self.images.push(Image {
+                width,
+                height,
+                colors,
+            });
+        }
+        self.names.push((name, index));
+    }
+
+    // Estantes: los tiles se ordenan por alto y se acomodan en filas de un ancho fijo
+    fn pack(self) -> (Texture, HashMap<String, UvRect>) {
+        if self.images.is_empty() {
+            return (
+                Texture::from_colors(1, 1, vec![Color::black()]),
+                HashMap::new(),
+            );
+        }
+
+        let alignment = self
+            .images
+            .iter()
+            .map(|image| 1 << (image.width | image.height).trailing_zeros())
+            .fold(MAX_ALIGNMENT, usize::min);
+        let padding = PADDING.next_multiple_of(alignment);
+
+        let area: usize = self
+            .images
+            .iter()
+            .map(|image| (image.width + 2 * padding) * (image.height + 2 * padding))
+            .sum();
+        let widest = self
+            .images
+            .iter()
+            .map(|image| image.width + 2 * padding)
+            .max()
+            .unwrap_or(1);
+        let width = ((area as f32).sqrt().ceil() as usize)
+            .max(widest)
+            .next_power_of_two();
+
+        let mut order: Vec<usize> = (0..self.images.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(self.images[i].height));
+
+        let mut positions = vec![(0, 0); self.images.len()];
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+        for &i in &order {
+            let image = &self.images[i];
+            let (w, h) = (image.width + 2 * padding, image.height + 2 * padding);
+            if x + w > width {
+                x = 0;
+                y += shelf_height;
+                shelf_height = 0;
+            }
+            positions[i] = (x + padding, y + padding);
+            x += w;
+            shelf_height = shelf_height.max(h);
+        }
+        let height = y + shelf_height;
+
+        let mut colors = vec![Color::black(); width * height];
+        for (image, &(left, top)) in self.images.iter().zip(&positions) {
+            // El relleno repite el texel más cercano del borde
+            for py in 0..image.height + 2 * padding {
+                for px in 0..image.width + 2 * padding {
+                    let sx = px.saturating_sub(padding).min(image.width - 1);
+                    let sy = py.saturating_sub(padding).min(image.height - 1);
+                    colors[(top - padding + py) * width + left - padding + px] =
+                        image.colors[sy * image.width + sx];
+                }
+            }
+        }
+
+        let rects = self
+            .names
+            .into_iter()
+            .map(|(name, index)| {
+                let image = &self.images[index];
+                let (left, top) = positions[index];
+                let rect = UvRect {
+                    u0: left as f32 / width as f32,
+                    u1: (left + image.width) as f32 / width as f32,
+                    v0: 1.0 - (top + image.height) as f32 / height as f32,
+                    v1: 1.0 - top as f32 / height as f32,
+                };
+                (name, rect)
+            })
+            .collect();
+
+        // Después de `alignment` los niveles mezclarían tiles vecinos
+        let levels = alignment.trailing_zeros() as usize + 1;
+        (Texture::with_levels(width, height, colors, levels), rects)
+    }
+}
+
+impl TextureRegistry {
+    // Carga todos los PNG de `folder`
+    pub fn load_folder(folder: impl AsRef<Path>) -> Result<TextureRegistry, AtlasError> {
+        let folder = folder.as_ref();
+        let error = |path: &Path, message: String| AtlasError {
+            path: path.to_path_buf(),
+            message,
+        };
+
+        let entries = std::fs::read_dir(folder).map_err(|e| error(folder, e.to_string()))?;
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+            .collect();
+        files.sort();
+
+        let mut color = Packer::default();
+        let mut data = Packer::default();
+        for path in &files {
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let img = ImageReader::open(path)
+                .map_err(image::ImageError::from)
+                .and_then(|reader| reader.decode())
+                .map_err(|e| error(path, e.to_string()))?;
+            let (width, height) = (img.width() as usize, img.height() as usize);
+            if width == 0 || height == 0 {
+                return Err(error(path, "image is empty".to_string()));
+            }
+
+            let is_data = DATA_SUFFIXES.iter().any(|suffix| name.ends_with(suffix));
+            let colors = decode_colors(&img, !is_data);
+            let raw = img.to_rgb8().into_raw();
+            let packer = if is_data { &mut data } else { &mut color };
+            packer.add(name.to_string(), width, height, raw, colors);
+        }
+
+        let (color, color_tiles) = color.pack();
+        let (data, data_tiles) = data.pack();
+        Ok(TextureRegistry {
+            folder: folder.to_path_buf(),
+            color: Arc::new(color),
+            data: Arc::new(data),
+            color_tiles,
+            data_tiles,
+            files,
+        })
+    }
+
+    // Tile de color (texturas base y de emisión)
+    pub fn texture(&self, name: &str) -> Option<TextureView> {
+        self.color_tiles.get(name).map(|&rect| TextureView {
+            texture: self.color.clone(),
+            rect,
+        })
+    }
+
+    // Tile de datos (mapas de normales, rugosidad y metalicidad)
+    pub fn data(&self, name: &str) -> Option<TextureView> {
+        self.data_tiles.get(name).map(|&rect| TextureView {
+            texture: self.data.clone(),
+            rect,
+        })
+    }
+
+    // Archivo en el que se buscaría el tile `name`, para los mensajes de error
+    pub fn path_of(&self, name: &str) -> PathBuf {
+        self.folder.join(format!("{name}.png"))
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+}
@@ -0,0 +1,76 @@
+use clap::Parser;
+use image::{GenericImageView, ImageReader, Rgb, RgbImage};
+use nalgebra_glm::Vec3;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(about = "Genera un mapa de normales a partir del brillo de una textura")]
+struct Args {
+    /// Textura de entrada
+    input: PathBuf,
+
+    /// Imagen de salida; por defecto `<entrada>_normal.png` junto a la entrada
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Qué tan pronunciado se ve el relieve
+    #[arg(short, long, default_value_t = 2.0)]
+    strength: f32,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let img = match ImageReader::open(&args.input)
+        .map_err(image::ImageError::from)
+        .and_then(|reader| reader.decode())
+    {
+        Ok(img) => img,
+        Err(e) => {
+            eprintln!("Failed to read {}: {e}", args.input.display());
+            std::process::exit(1);
+        }
+    };
+
+    let (width, height) = img.dimensions();
+
+    // Altura = brillo del texel; las grietas oscuras quedan hundidas y lo claro en relieve
+    let heights: Vec<f32> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let p = img.get_pixel(x, y);
+            let alpha = p[3] as f32 / 255.0;
+            (0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32) / 255.0 * alpha
+        })
+        .collect();
+    let height_at = |x: i64, y: i64| {
+        let x = x.clamp(0, width as i64 - 1) as usize;
+        let y = y.clamp(0, height as i64 - 1) as usize;
+        heights[y * width as usize + x]
+    };
+
+    let normal_map = RgbImage::from_fn(width, height, |x, y| {
+        let (x, y) = (x as i64, y as i64);
+
+        // Sobel
+        let dx = (height_at(x + 1, y - 1) + 2.0 * height_at(x + 1, y) + height_at(x + 1, y + 1))
+            - (height_at(x - 1, y - 1) + 2.0 * height_at(x - 1, y) + height_at(x - 1, y + 1));
+        let dy = (height_at(x - 1, y + 1) + 2.0 * height_at(x, y + 1) + height_at(x + 1, y + 1))
+            - (height_at(x - 1, y - 1) + 2.0 * height_at(x, y - 1) + height_at(x + 1, y - 1));
+
+        // En la imagen y crece hacia abajo, pero la bitangente (v) apunta hacia arriba
+        let normal = Vec3::new(-dx * args.strength, dy * args.strength, 1.0).normalize();
+        let encode = |c: f32| ((c * 0.5 + 0.5) * 255.0).round() as u8;
+        Rgb([encode(normal.x), encode(normal.y), encode(normal.z)])
+    });
+
+    let output = args.output.unwrap_or_else(|| {
+        let stem = args.input.file_stem().unwrap_or_default().to_string_lossy();
+        args.input.with_file_name(format!("{stem}_normal.png"))
+    });
+    if let Err(e) = normal_map.save(&output) {
+        eprintln!("Failed to write {}: {e}", output.display());
+        std::process::exit(1);
+    }
+    println!("{}", output.display());
+}
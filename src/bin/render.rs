@@ -0,0 +1,168 @@
+use clap::Parser;
+use nalgebra_glm::Vec3;
+use raytracing::{
+    load_scene, render, Bvh, Filter, Framebuffer, Integrator, Operator, RenderOptions,
+    SamplePattern, TextureFilter,
+};
+use std::path::PathBuf;
+use std::time::Instant;
+
+#[derive(Parser)]
+#[command(about = "Render sin ventana del diorama de Minecraft")]
+struct Args {
+    /// Archivo de escena
+    #[arg(default_value = "scenes/diorama.toml")]
+    scene: PathBuf,
+
+    /// Imagen de salida (PNG, o EXR para guardar la radiancia HDR sin tone mapping)
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Ancho de la imagen en píxeles
+    #[arg(long, default_value_t = 800)]
+    width: usize,
+
+    /// Alto de la imagen en píxeles
+    #[arg(long, default_value_t = 600)]
+    height: usize,
+
+    /// Muestras por píxel
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    samples: u32,
+
+    /// Reparto de las muestras en el píxel: jittered o stratified
+    #[arg(long, default_value = "stratified")]
+    pattern: SamplePattern,
+
+    /// Filtro de reconstrucción: box, tent, gaussian o mitchell
+    #[arg(long, default_value = "box")]
+    filter: Filter,
+
+    /// Filtrado de las texturas: nearest, bilinear o trilinear (con mipmaps)
+    #[arg(long, default_value = "trilinear")]
+    textures: TextureFilter,
+
+    /// Integrador: whitted (luz directa, reflexión y refracción) o path (path tracing con
+    /// iluminación global)
+    #[arg(long, default_value = "whitted")]
+    integrator: Integrator,
+
+    /// Instante de las texturas animadas, en segundos
+    #[arg(long, default_value_t = 0.0)]
+    time: f32,
+
+    /// Hora del día (0-24) si la escena tiene ciclo de día
+    #[arg(long)]
+    hour: Option<f32>,
+
+    /// Usa la textura del skybox aunque la escena tenga cielo analítico
+    #[arg(long)]
+    skybox: bool,
+
+    /// Curva de tone mapping: clamp, reinhard, aces o filmic
+    #[arg(long, default_value = "aces")]
+    tonemap: Operator,
+
+    /// Exposición en pasos (EV), p. ej. --exposure=-1
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    exposure: f32,
+
+    /// Calcula la exposición a partir de la luminancia media del cuadro
+    #[arg(long)]
+    auto_exposure: bool,
+
+    /// Posición de la cámara, p. ej. --eye=-5,5,-10
+    #[arg(long, value_parser = parse_vec3, allow_hyphen_values = true)]
+    eye: Option<Vec3>,
+
+    /// Punto al que mira la cámara
+    #[arg(long, value_parser = parse_vec3, allow_hyphen_values = true)]
+    center: Option<Vec3>,
+
+    /// Vector "arriba" de la cámara
+    #[arg(long, value_parser = parse_vec3, allow_hyphen_values = true)]
+    up: Option<Vec3>,
+}
+
+fn parse_vec3(value: &str) -> Result<Vec3, String> {
+    let parts: Vec<f32> = value
+        .split(',')
+        .map(|p| p.trim().parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    match parts[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => Err(format!("expected x,y,z but got `{value}`")),
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut scene = match load_scene(&args.scene) {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("Failed to load scene: {e}");
+            std::process::exit(1);
+        }
+    };
+    if let Some(eye) = args.eye {
+        scene.camera.eye = eye;
+    }
+    if let Some(center) = args.center {
+        scene.camera.center = center;
+    }
+    if let Some(up) = args.up {
+        scene.camera.up = up;
+    }
+
+    if let (Some(hour), Some(cycle)) = (args.hour, &mut scene.day_cycle) {
+        cycle.hour = hour.rem_euclid(24.0);
+    }
+
+    let mut environment = scene.environment();
+    if args.skybox {
+        environment.sky = None;
+    }
+
+    let objects = Bvh::new(scene.objects());
+    let mut framebuffer = Framebuffer::new(args.width, args.height);
+    framebuffer.tone_mapping.operator = args.tonemap;
+    framebuffer.tone_mapping.exposure = args.exposure;
+    framebuffer.tone_mapping.auto_exposure = args.auto_exposure;
+
+    let start = Instant::now();
+    render(
+        &mut framebuffer,
+        &objects,
+        &scene.camera,
+        &scene.lights(),
+        &environment,
+        &RenderOptions {
+            samples: args.samples,
+            time: args.time,
+            integrator: args.integrator,
+            pattern: args.pattern,
+            filter: args.filter,
+            texture_filter: args.textures,
+        },
+    );
+    let elapsed = start.elapsed();
+
+    let output = &args.output;
+    if let Err(e) = framebuffer.save(output) {
+        eprintln!("Failed to write {}: {e}", output.display());
+        std::process::exit(1);
+    }
+    println!(
+        "{} ({}x{}, {} spp, {}, {} filter, {} textures) in {:.2} s",
+        output.display(),
+        args.width,
+        args.height,
+        args.samples,
+        args.integrator,
+        args.filter,
+        args.textures,
+        elapsed.as_secs_f32()
+    );
+}
@@ -0,0 +1,81 @@
+use clap::Parser;
+use image::{ImageReader, Rgb, RgbImage};
+use std::path::PathBuf;
+
+// Las texturas originales del diorama son el desplegado de un cubo en 3 columnas por 4 filas
+const COLUMNS: f32 = 3.0;
+const ROWS: f32 = 4.0;
+
+#[derive(Parser)]
+#[command(about = "Recorta una cara de una textura desplegada de cubo y la guarda como tile")]
+struct Args {
+    /// Textura desplegada (3 columnas por 4 filas de caras)
+    input: PathBuf,
+
+    /// Columna y fila de la cara, por ejemplo 1,0
+    #[arg(short, long, value_parser = parse_cell)]
+    cell: (u32, u32),
+
+    /// Píxeles que se descartan en cada borde de la cara
+    #[arg(short, long, default_value_t = 0)]
+    margin: u32,
+
+    /// Lado del tile en texels
+    #[arg(long, default_value_t = 16)]
+    size: u32,
+
+    /// Imagen de salida
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+fn parse_cell(value: &str) -> Result<(u32, u32), String> {
+    let parts: Vec<u32> = value
+        .split(',')
+        .map(|p| p.trim().parse::<u32>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    match parts[..] {
+        [column, row] if (column as f32) < COLUMNS && (row as f32) < ROWS => Ok((column, row)),
+        _ => Err(format!(
+            "expected column,row inside a 3x4 grid but got `{value}`"
+        )),
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let img = match ImageReader::open(&args.input)
+        .map_err(image::ImageError::from)
+        .and_then(|reader| reader.decode())
+    {
+        Ok(img) => img.to_rgb8(),
+        Err(e) => {
+            eprintln!("Failed to read {}: {e}", args.input.display());
+            std::process::exit(1);
+        }
+    };
+
+    let cell_width = img.width() as f32 / COLUMNS;
+    let cell_height = img.height() as f32 / ROWS;
+    let margin = args.margin as f32;
+    let (column, row) = (args.cell.0 as f32, args.cell.1 as f32);
+    let size = args.size as f32;
+
+    // Cada texel del tile toma el píxel del centro de su celda (vecino más cercano)
+    let tile = RgbImage::from_fn(args.size, args.size, |x, y| {
+        let source_x =
+            column * cell_width + margin + (x as f32 + 0.5) * (cell_width - 2.0 * margin) / size;
+        let source_y =
+            row * cell_height + margin + (y as f32 + 0.5) * (cell_height - 2.0 * margin) / size;
+        let p = img.get_pixel(source_x as u32, source_y as u32);
+        Rgb([p[0], p[1], p[2]])
+    });
+
+    if let Err(e) = tile.save(&args.output) {
+        eprintln!("Failed to write {}: {e}", args.output.display());
+        std::process::exit(1);
+    }
+    println!("{}", args.output.display());
+}
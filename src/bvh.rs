@@ -0,0 +1,436 @@
+use crate::aabb::Aabb;
+use crate::ray_intersect::{Intersect, RayIntersect};
+use nalgebra_glm::Vec3;
+
+const SAH_BINS: usize = 12;
+const MAX_LEAF_SIZE: usize = 2;
+const TRAVERSAL_COST: f32 = 1.0;
+const INTERSECTION_COST: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy)]
+struct BvhNode {
+    bounds: Aabb,
+    // Hoja: primer índice en `indices`. Nodo interno: índice del hijo izquierdo (el derecho va después).
+    first: usize,
+    // Número de objetos en la hoja; 0 para nodos internos.
+    count: usize,
+}
+
+impl BvhNode {
+    fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Bin {
+    bounds: Aabb,
+    count: usize,
+}
+
+pub struct Bvh<T: RayIntersect> {
+    objects: Vec<T>,
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>,
+    linear_scan: bool,
+}
+
+impl<T: RayIntersect> Bvh<T> {
+    pub fn new(objects: Vec<T>) -> Self {
+        let mut bvh = Bvh {
+            objects,
+            nodes: Vec::new(),
+            indices: Vec::new(),
+            linear_scan: false,
+        };
+        bvh.rebuild();
+        bvh
+    }
+
+    pub fn objects(&self) -> &[T] {
+        &self.objects
+    }
+
+    // Tras modificar objetos hay que llamar a `refit` (si solo se movieron) o `rebuild`.
+    pub fn objects_mut(&mut self) -> &mut [T] {
+        &mut self.objects
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_linear_scan(&self) -> bool {
+        self.linear_scan
+    }
+
+    // Permite comparar contra el recorrido lineal original sin cambiar la escena.
+    pub fn set_linear_scan(&mut self, enabled: bool) {
+        self.linear_scan = enabled;
+    }
+
+    pub fn rebuild(&mut self) {
+        self.nodes.clear();
+        self.indices = (0..self.objects.len()).collect();
+
+        if self.objects.is_empty() {
+            return;
+        }
+
+        let bounds: Vec<Aabb> = self.objects.iter().map(|o| o.aabb()).collect();
+        let centroids: Vec<Vec3> = bounds.iter().map(|b| b.centroid()).collect();
+
+        self.nodes.push(BvhNode {
+            bounds: Aabb::empty(),
+            first: 0,
+            count: self.objects.len(),
+        });
+        self.subdivide(0, &bounds, &centroids);
+    }
+
+    // Recalcula las cajas sin cambiar la topología; sirve cuando los objetos se mueven poco.
+    pub fn refit(&mut self) {
+        // Los hijos siempre se insertan después de su padre, así que basta recorrer al revés.
+        for i in (0..self.nodes.len()).rev() {
+            let node = self.nodes[i];
+            let bounds = if node.is_leaf() {
+                self.indices[node.first..node.first + node.count]
+                    .iter()
+                    .fold(Aabb::empty(), |acc, &idx| {
+                        acc.union(&self.objects[idx].aabb())
+                    })
+            } else {
+                self.nodes[node.first]
+                    .bounds
+                    .union(&self.nodes[node.first + 1].bounds)
+            };
+            self.nodes[i].bounds = bounds;
+        }
+    }
+
+    fn subdivide(&mut self, node_index: usize, bounds: &[Aabb], centroids: &[Vec3]) {
+        let first = self.nodes[node_index].first;
+        let count = self.nodes[node_index].count;
+        let range = first..first + count;
+
+        let mut node_bounds = Aabb::empty();
+        let mut centroid_bounds = Aabb::empty();
+        for &idx in &self.indices[range.clone()] {
+            node_bounds = node_bounds.union(&bounds[idx]);
+            centroid_bounds.grow(&centroids[idx]);
+        }
+        self.nodes[node_index].bounds = node_bounds;
+
+        if count <= MAX_LEAF_SIZE {
+            return;
+        }
+
+        let Some((axis, split_pos, split_cost)) =
+            self.find_best_split(range, &node_bounds, bounds, centroids, &centroid_bounds)
+        else {
+            return;
+        };
+
+        let leaf_cost = INTERSECTION_COST * count as f32;
+        if split_cost >= leaf_cost {
+            return;
+        }
+
+        // Partición en sitio de los índices según el plano elegido
+        let mut i = first;
+        let mut j = first + count;
+        while i < j {
+            if centroids[self.indices[i]][axis] < split_pos {
+                i += 1;
+            } else {
+                j -= 1;
+                self.indices.swap(i, j);
+            }
+        }
+
+        let left_count = i - first;
+        if left_count == 0 || left_count == count {
+            return;
+        }
+
+        let left = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bounds: Aabb::empty(),
+            first,
+            count: left_count,
+        });
+        self.nodes.push(BvhNode {
+            bounds: Aabb::empty(),
+            first: i,
+            count: count - left_count,
+        });
+        self.nodes[node_index].first = left;
+        self.nodes[node_index].count = 0;
+
+        self.subdivide(left, bounds, centroids);
+        self.subdivide(left + 1, bounds, centroids);
+    }
+
+    // SAH por bins: devuelve (eje, posición del plano, costo estimado)
+    fn find_best_split(
+        &self,
+        range: std::ops::Range<usize>,
+        node_bounds: &Aabb,
+        bounds: &[Aabb],
+        centroids: &[Vec3],
+        centroid_bounds: &Aabb,
+    ) -> Option<(usize, f32, f32)> {
+        let parent_area = node_bounds.surface_area();
+        if parent_area <= 0.0 {
+            return None;
+        }
+
+        let mut best: Option<(usize, f32, f32)> = None;
+
+        let axis_ranges = centroid_bounds.min.iter().zip(centroid_bounds.max.iter());
+        for (axis, (&axis_min, &axis_max)) in axis_ranges.enumerate() {
+            if axis_max - axis_min < 1e-6 {
+                continue;
+            }
+
+            let mut bins = [Bin {
+                bounds: Aabb::empty(),
+                count: 0,
+            }; SAH_BINS];
+            let scale = SAH_BINS as f32 / (axis_max - axis_min);
+
+            for &idx in &self.indices[range.clone()] {
+                let b = (((centroids[idx][axis] - axis_min) * scale) as usize).min(SAH_BINS - 1);
+                bins[b].count += 1;
+                bins[b].bounds = bins[b].bounds.union(&bounds[idx]);
+            }
+
+            let mut left_area = [0.0; SAH_BINS - 1];
+            let mut left_count = [0; SAH_BINS - 1];
+            let mut acc_bounds = Aabb::empty();
+            let mut acc_count = 0;
+            for i in 0..SAH_BINS - 1 {
+                acc_bounds = acc_bounds.union(&bins[i].bounds);
+                acc_count += bins[i].count;
+                left_area[i] = acc_bounds.surface_area();
+                left_count[i] = acc_count;
+            }
+
+            let mut acc_bounds = Aabb::empty();
+            let mut acc_count = 0;
+            for i in (1..SAH_BINS).rev() {
+                acc_bounds = acc_bounds.union(&bins[i].bounds);
+                acc_count += bins[i].count;
+
+                let l = left_count[i - 1];
+                if l == 0 || acc_count == 0 {
+                    continue;
+                }
+
+                let cost = TRAVERSAL_COST
+                    + INTERSECTION_COST
+                        * (left_area[i - 1] * l as f32
+                            + acc_bounds.surface_area() * acc_count as f32)
+                        / parent_area;
+
+                if best.is_none_or(|(_, _, c)| cost < c) {
+                    let split_pos = axis_min + i as f32 / scale;
+                    best = Some((axis, split_pos, cost));
+                }
+            }
+        }
+
+        best
+    }
+
+    pub fn intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        if self.linear_scan {
+            return self.intersect_linear(ray_origin, ray_direction);
+        }
+
+        let mut closest = Intersect::empty();
+        let mut zbuffer = f32::INFINITY;
+        self.traverse(ray_origin, ray_direction, f32::INFINITY, |i| {
+            if i.distance < zbuffer {
+                zbuffer = i.distance;
+                closest = i;
+            }
+            false
+        });
+        closest
+    }
+
+    // Cualquier intersección antes de `max_distance`; suficiente para rayos de sombra.
+    pub fn any_hit(
+        &self,
+        ray_origin: &Vec3,
+        ray_direction: &Vec3,
+        max_distance: f32,
+    ) -> Option<Intersect> {
+        if self.linear_scan {
+            return self
+                .objects
+                .iter()
+                .map(|object| object.ray_intersect(ray_origin, ray_direction))
+                .find(|i| i.is_intersecting && i.distance < max_distance);
+        }
+
+        let mut hit = None;
+        self.traverse(ray_origin, ray_direction, max_distance, |i| {
+            if i.distance < max_distance {
+                hit = Some(i);
+                true
+            } else {
+                false
+            }
+        });
+        hit
+    }
+
+    pub fn intersect_linear(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        let mut intersect = Intersect::empty();
+        let mut zbuffer = f32::INFINITY;
+
+        for object in &self.objects {
+            let i = object.ray_intersect(ray_origin, ray_direction);
+            if i.is_intersecting && i.distance < zbuffer {
+                zbuffer = i.distance;
+                intersect = i;
+            }
+        }
+
+        intersect
+    }
+
+    // Recorre el árbol en orden cercano-a-lejano; `on_hit` devuelve true para terminar antes.
+    fn traverse<F>(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_distance: f32, mut on_hit: F)
+    where
+        F: FnMut(Intersect) -> bool,
+    {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let inv_dir = Vec3::new(
+            1.0 / ray_direction.x,
+            1.0 / ray_direction.y,
+            1.0 / ray_direction.z,
+        );
+
+        let mut t_closest = max_distance;
+        let mut stack: Vec<usize> = Vec::with_capacity(64);
+        stack.push(0);
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if node.bounds.hit(ray_origin, &inv_dir, t_closest).is_none() {
+                continue;
+            }
+
+            if node.is_leaf() {
+                for &idx in &self.indices[node.first..node.first + node.count] {
+                    let i = self.objects[idx].ray_intersect(ray_origin, ray_direction);
+                    if i.is_intersecting && i.distance < t_closest {
+                        t_closest = i.distance;
+                        if on_hit(i) {
+                            return;
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let left = node.first;
+            let right = node.first + 1;
+            let t_left = self.nodes[left].bounds.hit(ray_origin, &inv_dir, t_closest);
+            let t_right = self.nodes[right]
+                .bounds
+                .hit(ray_origin, &inv_dir, t_closest);
+
+            match (t_left, t_right) {
+                (Some(tl), Some(tr)) => {
+                    // El más cercano se apila al final para visitarlo primero
+                    if tl <= tr {
+                        stack.push(right);
+                        stack.push(left);
+                    } else {
+                        stack.push(left);
+                        stack.push(right);
+                    }
+                }
+                (Some(_), None) => stack.push(left),
+                (None, Some(_)) => stack.push(right),
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::cube::Cube;
+    use crate::material::Material;
+    use crate::sampling::Rng;
+
+    fn random_vec(rng: &mut Rng, min: f32, max: f32) -> Vec3 {
+        Vec3::new(
+            min + rng.next_f32() * (max - min),
+            min + rng.next_f32() * (max - min),
+            min + rng.next_f32() * (max - min),
+        )
+    }
+
+    // Cada cubo guarda su índice en la rugosidad para saber a cuál le pegó el rayo
+    fn random_cubes(rng: &mut Rng, count: usize) -> Vec<Cube> {
+        (0..count)
+            .map(|i| {
+                let min = random_vec(rng, -10.0, 10.0);
+                Cube {
+                    min,
+                    max: min + random_vec(rng, 0.1, 2.0),
+                    material: Material::new(Color::black(), i as f32, 0.0, 1.0, Color::black()),
+                }
+            })
+            .collect()
+    }
+
+    fn assert_matches_linear(bvh: &Bvh<Cube>, rng: &mut Rng) {
+        for _ in 0..1000 {
+            let origin = random_vec(rng, -15.0, 15.0);
+            let direction = (random_vec(rng, -8.0, 8.0) - origin).normalize();
+
+            let expected = bvh.intersect_linear(&origin, &direction);
+            let hit = bvh.intersect(&origin, &direction);
+            assert_eq!(hit.is_intersecting, expected.is_intersecting);
+            if expected.is_intersecting {
+                assert_eq!(hit.distance, expected.distance);
+                assert_eq!(hit.material.roughness, expected.material.roughness);
+            }
+
+            let max_distance = rng.next_f32() * 30.0;
+            let blocked = expected.is_intersecting && expected.distance < max_distance;
+            let any = bvh.any_hit(&origin, &direction, max_distance);
+            assert_eq!(any.is_some(), blocked);
+            if let Some(any) = any {
+                assert!(any.distance < max_distance);
+            }
+        }
+    }
+
+    #[test]
+    fn matches_linear_scan() {
+        let mut rng = Rng::new(7);
+        let mut bvh = Bvh::new(random_cubes(&mut rng, 300));
+        assert_matches_linear(&bvh, &mut rng);
+
+        for cube in bvh.objects_mut() {
+            let offset = random_vec(&mut rng, -1.0, 1.0);
+            cube.min += offset;
+            cube.max += offset;
+        }
+        bvh.refit();
+        assert_matches_linear(&bvh, &mut rng);
+    }
+}
@@ -1,29 +1,80 @@
+use once_cell::sync::Lazy;
 use std::fmt;
 
-#[derive(Debug, Clone, Copy)]
+// Radiancia en RGB lineal; los valores pueden pasar de 1.0 hasta que se convierte a 8 bits
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8,
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+// Tabla de decodificación sRGB -> lineal para los 256 valores de un canal de 8 bits
+static SRGB_TO_LINEAR: Lazy<[f32; 256]> = Lazy::new(|| {
+    let mut table = [0.0; 256];
+    for (i, value) in table.iter_mut().enumerate() {
+        *value = srgb_to_linear(i as f32 / 255.0);
+    }
+    table
+});
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
 }
 
 impl Color {
-    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+    pub const fn new(r: f32, g: f32, b: f32) -> Self {
         Color { r, g, b }
     }
-    pub const fn from_hex(hex: u32) -> Self {
+
+    // Color de 8 bits en sRGB (como en una imagen o un selector de color)
+    pub fn from_srgb8(r: u8, g: u8, b: u8) -> Self {
+        Color {
+            r: SRGB_TO_LINEAR[r as usize],
+            g: SRGB_TO_LINEAR[g as usize],
+            b: SRGB_TO_LINEAR[b as usize],
+        }
+    }
+
+    pub fn from_hex(hex: u32) -> Self {
         let r = ((hex >> 16) & 0xFF) as u8;
         let g = ((hex >> 8) & 0xFF) as u8;
         let b = (hex & 0xFF) as u8;
-        Color { r, g, b }
+        Color::from_srgb8(r, g, b)
     }
 
     pub const fn black() -> Self {
-        Color { r: 0, g: 0, b: 0 }
+        Color {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+        }
+    }
+
+    pub fn is_black(&self) -> bool {
+        self.r <= 0.0 && self.g <= 0.0 && self.b <= 0.0
     }
 
-    pub const fn to_hex(&self) -> u32 {
-        ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
+    // Codifica a sRGB de 8 bits; solo se usa al final, al escribir el framebuffer
+    pub fn to_hex(self) -> u32 {
+        let encode = |c: f32| (linear_to_srgb(c.clamp(0.0, 1.0)) * 255.0).round() as u32;
+        (encode(self.r) << 16) | (encode(self.g) << 8) | encode(self.b)
+    }
+
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
     }
 }
 
@@ -34,9 +85,9 @@ impl Add for Color {
 
     fn add(self, other: Color) -> Color {
         Color {
-            r: self.r.saturating_add(other.r),
-            g: self.g.saturating_add(other.g),
-            b: self.b.saturating_add(other.b),
+            r: self.r + other.r,
+            g: self.g + other.g,
+            b: self.b + other.b,
         }
     }
 }
@@ -48,9 +99,21 @@ impl Mul<f32> for Color {
 
     fn mul(self, scalar: f32) -> Color {
         Color {
-            r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
-            g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
-            b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
+            r: self.r * scalar,
+            g: self.g * scalar,
+            b: self.b * scalar,
+        }
+    }
+}
+
+impl Mul<Color> for Color {
+    type Output = Color;
+
+    fn mul(self, other: Color) -> Color {
+        Color {
+            r: self.r * other.r,
+            g: self.g * other.g,
+            b: self.b * other.b,
         }
     }
 }
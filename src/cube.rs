@@ -1,3 +1,4 @@
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::ray_intersect::{Intersect, RayIntersect};
 use nalgebra_glm::Vec3;
@@ -10,59 +11,108 @@ pub struct Cube {
 
 impl Cube {
     pub fn get_uv(&self, point: &Vec3, normal: &Vec3) -> (f32, f32) {
-        let size = self.max - self.min;
-        let local_point = point - self.min;
-
-        let img_width = 375.0;
-        let img_height = 500.0;
-        let num_columns = 3.0;
-        let num_rows = 4.0;
-
-        let column_width = img_width / num_columns;
-        let row_height = img_height / num_rows;
-
-        let (u, v) = if normal.x > 0.0 {
-            // Cara derecha (Face 6)
-            (
-                (local_point.y / size.y) * column_width + 2.0 * column_width, // Columna 3
-                (1.0 - local_point.z / size.z) * row_height + row_height,
-            ) // Fila 2
+        face_uv(
+            &(point - self.min),
+            &(self.max - self.min),
+            Face::from_normal(normal),
+        )
+    }
+}
+
+// Caras de un bloque con los nombres de Minecraft: el norte es -z y el este +x
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Face {
+    Top,
+    Bottom,
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face::Top,
+        Face::Bottom,
+        Face::North,
+        Face::South,
+        Face::East,
+        Face::West,
+    ];
+
+    pub fn from_normal(normal: &Vec3) -> Face {
+        if normal.x > 0.0 {
+            Face::East
         } else if normal.x < 0.0 {
-            // Cara izquierda (Face 4)
-            (
-                (1.0 - local_point.y / size.y) * column_width, // Columna 1
-                (1.0 - local_point.z / size.z) * row_height + row_height,
-            ) // Fila 2
+            Face::West
         } else if normal.y > 0.0 {
-            // Cara superior (Face 11)
-            (
-                (local_point.x / size.x) * column_width + column_width, // Columna 2
-                (local_point.z / size.z) * row_height + 3.0 * row_height,
-            ) // Fila 4
+            Face::Top
         } else if normal.y < 0.0 {
-            // Cara inferior (Face 5)
-            (
-                (local_point.x / size.x) * column_width + column_width, // Columna 2
-                (local_point.z / size.z) * row_height + row_height,
-            ) // Fila 2
+            Face::Bottom
         } else if normal.z > 0.0 {
-            // Cara frontal (Face 2)
-            (
-                (local_point.x / size.x) * column_width + column_width, // Columna 2
-                (1.0 - local_point.y / size.y) * row_height,
-            ) // Fila 1
+            Face::South
         } else {
-            // Cara trasera (Face 8)
-            (
-                (local_point.x / size.x) * column_width + column_width, // Columna 2
-                (local_point.y / size.y) * row_height + 2.0 * row_height,
-            ) // Fila 3
-        };
-
-        (u / img_width, 1.0 - v / img_height)
+            Face::North
+        }
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
     }
 }
 
+// UV dentro de la cara, de 0 a 1, para un punto local a la caja de tamaño `size`. Las caras
+// laterales se ven derechas desde afuera (v hacia arriba) y la de arriba tiene el norte arriba.
+pub fn face_uv(local_point: &Vec3, size: &Vec3, face: Face) -> (f32, f32) {
+    let x = local_point.x / size.x;
+    let y = local_point.y / size.y;
+    let z = local_point.z / size.z;
+    match face {
+        Face::East => (1.0 - z, y),
+        Face::West => (z, y),
+        Face::South => (x, y),
+        Face::North => (1.0 - x, y),
+        Face::Top => (x, 1.0 - z),
+        Face::Bottom => (x, z),
+    }
+}
+
+// Direcciones en las que crecen u y v en cada cara de `face_uv`
+pub fn face_tangents(face: Face) -> (Vec3, Vec3) {
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    match face {
+        Face::East => (Vec3::new(0.0, 0.0, -1.0), up),
+        Face::West => (Vec3::new(0.0, 0.0, 1.0), up),
+        Face::South => (Vec3::new(1.0, 0.0, 0.0), up),
+        Face::North => (Vec3::new(-1.0, 0.0, 0.0), up),
+        Face::Top => (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)),
+        Face::Bottom => (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+    }
+}
+
+// Impacto en una cara de la caja que empieza en `min` y mide `size`, con la textura que el
+// material le asigna a esa cara
+pub fn face_intersect(
+    point: Vec3,
+    normal: Vec3,
+    distance: f32,
+    min: &Vec3,
+    size: &Vec3,
+    material: &Material,
+) -> Intersect {
+    let face = Face::from_normal(&normal);
+    let (u, v) = face_uv(&(point - min), size, face);
+    let (tangent, bitangent) = face_tangents(face);
+    let (span_u, span_v) = material.face_span(face);
+    let (material, (u, v), (tangent, bitangent)) = material.on_face(face, u, v, tangent, bitangent);
+    // Los ejes de la cara son los de la caja, así que su largo sale de `size`
+    let scale_u = tangent.abs().dot(size) / span_u;
+    let scale_v = bitangent.abs().dot(size) / span_v;
+    Intersect::new(point, normal, distance, material, u, v)
+        .with_tangents(tangent, bitangent)
+        .with_uv_scale(scale_u, scale_v)
+}
+
 impl RayIntersect for Cube {
     fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
         let inv_dir = Vec3::new(
@@ -85,7 +135,9 @@ impl RayIntersect for Cube {
         let t_exit = t2.min(t4).min(t6);
 
         if t_enter < t_exit && t_exit > 0.0 {
-            let point = ray_origin + ray_direction * t_enter;
+            // Si el rayo nace dentro del cubo choca con la cara por la que sale
+            let t_hit = if t_enter > 0.0 { t_enter } else { t_exit };
+            let point = ray_origin + ray_direction * t_hit;
             let normal = if (point[0] - self.min[0]).abs() < 1e-3 {
                 Vec3::new(-1.0, 0.0, 0.0)
             } else if (point[0] - self.max[0]).abs() < 1e-3 {
@@ -100,10 +152,14 @@ impl RayIntersect for Cube {
                 Vec3::new(0.0, 0.0, 1.0)
             };
 
-            let (u, v) = self.get_uv(&point, &normal);
-            return Intersect::new(point, normal, t_enter, self.material.clone(), u, v);
+            let size = self.max - self.min;
+            return face_intersect(point, normal, t_hit, &self.min, &size, &self.material);
         }
 
         Intersect::empty()
     }
+
+    fn aabb(&self) -> Aabb {
+        Aabb::new(self.min, self.max)
+    }
 }
@@ -0,0 +1,23 @@
+use clap::ValueEnum;
+use std::fmt;
+
+// Opciones con nombre (filtros, integradores...): se eligen por nombre en la línea de comandos y
+// en el visor se recorren con una tecla
+pub trait Cycle: ValueEnum + PartialEq {
+    // La opción siguiente; después de la última vuelve a la primera
+    fn next(&self) -> Self {
+        let all = Self::value_variants();
+        let index = all.iter().position(|value| value == self).unwrap_or(0);
+        all[(index + 1) % all.len()].clone()
+    }
+}
+
+impl<T: ValueEnum + PartialEq> Cycle for T {}
+
+// Escribe el nombre con el que se elige la opción; sirve para implementar `Display`
+pub fn write_name(value: &impl ValueEnum, f: &mut fmt::Formatter) -> fmt::Result {
+    match value.to_possible_value() {
+        Some(name) => f.write_str(name.get_name()),
+        None => Ok(()),
+    }
+}
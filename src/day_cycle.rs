@@ -0,0 +1,96 @@
+use crate::color::Color;
+use crate::light::Light;
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+// Inclinación de la órbita del sol hacia -z, para que al mediodía ilumine el frente del diorama
+const ORBIT_TILT: f32 = 40.0 * PI / 180.0;
+
+const NOON_SUN: Color = Color::new(1.0, 0.95, 0.87);
+const HORIZON_SUN: Color = Color::new(1.0, 0.38, 0.1);
+const MOON: Color = Color::new(0.5, 0.6, 1.0);
+// Luz que llega del cielo en las caras que no ven al sol
+const DAY_AMBIENT: Color = Color::new(0.04, 0.05, 0.07);
+const NIGHT_AMBIENT: Color = Color::new(0.0008, 0.001, 0.003);
+
+#[derive(Debug, Clone)]
+pub struct DayCycle {
+    // Hora del día en [0, 24); el sol sale a las 6 y se pone a las 18
+    pub hour: f32,
+    // Horas del juego que pasan por cada segundo real
+    pub speed: f32,
+    pub sun_intensity: f32,
+    pub moon_intensity: f32,
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+impl DayCycle {
+    pub fn advance(&mut self, seconds: f32) {
+        self.hour = (self.hour + self.speed * seconds).rem_euclid(24.0);
+    }
+
+    // Dirección hacia el sol; la luna siempre está del lado opuesto
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = (self.hour - 6.0) / 24.0 * 2.0 * PI;
+        Vec3::new(
+            angle.cos(),
+            angle.sin() * ORBIT_TILT.cos(),
+            -angle.sin() * ORBIT_TILT.sin(),
+        )
+    }
+
+    fn sun_elevation(&self) -> f32 {
+        self.sun_direction().y
+    }
+
+    // 1 con el sol alto, 0 de noche, con una transición suave alrededor del horizonte
+    pub fn daylight(&self) -> f32 {
+        smoothstep(-0.1, 0.2, self.sun_elevation())
+    }
+
+    // Qué tan encendidos están los bloques emisivos: apagados de día, encendidos al atardecer
+    pub fn lamp_strength(&self) -> f32 {
+        1.0 - smoothstep(0.0, 0.25, self.sun_elevation())
+    }
+
+    // Blanco al mediodía y anaranjado cerca del horizonte
+    pub fn sun_color(&self) -> Color {
+        let t = smoothstep(0.0, 0.35, self.sun_elevation());
+        HORIZON_SUN * (1.0 - t) + NOON_SUN * t
+    }
+
+    pub fn ambient(&self) -> Color {
+        let t = self.daylight();
+        NIGHT_AMBIENT * (1.0 - t) + DAY_AMBIENT * t
+    }
+
+    // Luces direccionales del sol y la luna; se apagan al pasar bajo el horizonte. El color
+    // del sol lo decide la escena, que lo comparte con el cielo
+    pub fn lights(&self, sun_color: Color) -> Vec<Light> {
+        let sun_direction = self.sun_direction();
+        let elevation = sun_direction.y;
+
+        let mut lights = Vec::new();
+        let sun = self.sun_intensity * smoothstep(-0.05, 0.1, elevation);
+        if sun > 0.0 {
+            lights.push(Light::Directional {
+                direction: -sun_direction,
+                color: sun_color,
+                intensity: sun,
+            });
+        }
+        let moon = self.moon_intensity * smoothstep(-0.05, 0.1, -elevation);
+        if moon > 0.0 {
+            lights.push(Light::Directional {
+                direction: sun_direction,
+                color: MOON,
+                intensity: moon,
+            });
+        }
+        lights
+    }
+}
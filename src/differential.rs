@@ -0,0 +1,147 @@
+use crate::microfacet::tangent_frame;
+use crate::ray_intersect::Intersect;
+use nalgebra_glm::Vec3;
+
+// Diferenciales de un rayo (Igehy, 1999): cuánto cambian su origen y su dirección al pasar al
+// píxel de al lado en x y en y. Al chocar dan la huella del píxel sobre la superficie, y con
+// ella el mipmap que corresponde leer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RayDifferential {
+    pub dx_origin: Vec3,
+    pub dx_direction: Vec3,
+    pub dy_origin: Vec3,
+    pub dy_direction: Vec3,
+}
+
+// Los diferenciales en el punto de impacto: cuánto se mueve el punto sobre la superficie hacia
+// los píxeles vecinos, y las direcciones con las que llegaron
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceDifferential {
+    pub dx_point: Vec3,
+    pub dy_point: Vec3,
+    dx_direction: Vec3,
+    dy_direction: Vec3,
+}
+
+impl RayDifferential {
+    // Rayo primario: las direcciones de los píxeles de la derecha y de abajo menos la propia
+    pub fn from_camera(direction: &Vec3, right: &Vec3, below: &Vec3) -> Self {
+        RayDifferential {
+            dx_direction: right - direction,
+            dy_direction: below - direction,
+            ..Default::default()
+        }
+    }
+
+    pub fn scale(self, factor: f32) -> Self {
+        RayDifferential {
+            dx_origin: self.dx_origin * factor,
+            dx_direction: self.dx_direction * factor,
+            dy_origin: self.dy_origin * factor,
+            dy_direction: self.dy_direction * factor,
+        }
+    }
+
+    // Los rayos vecinos se cortan con el plano tangente del impacto. Usa la normal geométrica, así
+    // que hay que llamarlo antes de aplicar el mapa de normales.
+    pub fn at_surface(&self, direction: &Vec3, intersect: &Intersect) -> SurfaceDifferential {
+        let normal = intersect.normal;
+        // De canto la huella se vuelve infinita; se limita a algo grande
+        let cos = direction.dot(&normal);
+        let cos = if cos.abs() < 1e-3 {
+            1e-3_f32.copysign(cos)
+        } else {
+            cos
+        };
+        let transfer = |origin: &Vec3, offset: &Vec3| {
+            let point = origin + offset * intersect.distance;
+            point - direction * (point.dot(&normal) / cos)
+        };
+        SurfaceDifferential {
+            dx_point: transfer(&self.dx_origin, &self.dx_direction),
+            dy_point: transfer(&self.dy_origin, &self.dy_direction),
+            dx_direction: self.dx_direction,
+            dy_direction: self.dy_direction,
+        }
+    }
+}
+
+impl SurfaceDifferential {
+    // Huella del píxel en UV: cuánto cambian u y v, como máximo, hacia un píxel vecino
+    pub fn footprint(&self, intersect: &Intersect) -> (f32, f32) {
+        let (scale_u, scale_v) = intersect.uv_scale;
+        if scale_u <= 0.0 || scale_v <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let along = |axis: &Vec3, scale: f32| {
+            self.dx_point
+                .dot(axis)
+                .abs()
+                .max(self.dy_point.dot(axis).abs())
+                / scale
+        };
+        (
+            along(&intersect.tangent, scale_u),
+            along(&intersect.bitangent, scale_v),
+        )
+    }
+
+    // Rayo reflejado. Se toma la superficie como plana: la curvatura de las esferas no abre
+    // el reflejo.
+    pub fn reflect(&self, normal: &Vec3) -> RayDifferential {
+        let mirror = |offset: &Vec3| offset - normal * (2.0 * offset.dot(normal));
+        RayDifferential {
+            dx_origin: self.dx_point,
+            dx_direction: mirror(&self.dx_direction),
+            dy_origin: self.dy_point,
+            dy_direction: mirror(&self.dy_direction),
+        }
+    }
+
+    // Rayo refractado de `incident` a `refracted` al entrar en un medio de índice `ior`, o al
+    // salir si viene desde adentro, como en `renderer::refract`
+    pub fn refract(
+        &self,
+        incident: &Vec3,
+        refracted: &Vec3,
+        normal: &Vec3,
+        ior: f32,
+    ) -> RayDifferential {
+        let (eta, normal) = if incident.dot(normal) > 0.0 {
+            (ior, -normal)
+        } else {
+            (1.0 / ior, *normal)
+        };
+        let cos_i = incident.dot(&normal);
+        let cos_t = refracted.dot(&normal);
+        // La dirección refractada es η·d − μ·n; μ cambia con el ángulo de llegada
+        let dmu = if cos_t.abs() > 1e-6 {
+            eta - eta * eta * cos_i / cos_t
+        } else {
+            eta
+        };
+        let bend = |offset: &Vec3| offset * eta - normal * (dmu * offset.dot(&normal));
+        RayDifferential {
+            dx_origin: self.dx_point,
+            dx_direction: bend(&self.dx_direction),
+            dy_origin: self.dy_point,
+            dy_direction: bend(&self.dy_direction),
+        }
+    }
+
+    // Después de un rebote difuso o rugoso los rayos vecinos salen para cualquier lado; se
+    // aproxima con un cono de `spread` radianes alrededor de la nueva dirección. El cono no se
+    // cierra: nunca es más angosto que el del rayo que llegó.
+    pub fn scatter(&self, direction: &Vec3, spread: f32) -> RayDifferential {
+        let spread = spread
+            .max(self.dx_direction.magnitude())
+            .max(self.dy_direction.magnitude());
+        let (tangent, bitangent) = tangent_frame(direction);
+        RayDifferential {
+            dx_origin: self.dx_point,
+            dx_direction: tangent * spread,
+            dy_origin: self.dy_point,
+            dy_direction: bitangent * spread,
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::color::Color;
+use crate::sky::Sky;
+use crate::texture::Texture;
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+// (25, 25, 25) en sRGB
+const AMBIENT_LIGHT_COLOR: Color = Color::new(0.0097, 0.0097, 0.0097);
+const AMBIENT_INTENSITY: f32 = 0.3;
+
+// Tamaño angular (coseno del radio) de los discos del sol y la luna en el cielo
+const SUN_DISC: f32 = 0.9995;
+const MOON_DISC: f32 = 0.9993;
+const SUN_RADIANCE: f32 = 30.0;
+const MOON_RADIANCE: f32 = 1.5;
+
+// Todo lo que no es geometría: el fondo, la luz ambiental y qué tan encendidos están los
+// materiales emisivos.
+#[derive(Clone)]
+pub struct Environment {
+    pub skybox: Arc<Texture>,
+    // Cielo analítico; si está, reemplaza a `skybox` de día
+    pub sky: Option<Sky>,
+    // Se mezcla con `skybox` según `daylight`
+    pub night_skybox: Option<Arc<Texture>>,
+    // 1 de día, 0 de noche
+    pub daylight: f32,
+    pub ambient: Color,
+    // Factor de la emisión de los materiales
+    pub emission: f32,
+    // Dirección hacia el sol (y su color), para dibujar el sol y la luna en el cielo
+    pub sun: Option<(Vec3, Color)>,
+}
+
+impl Environment {
+    pub fn new(skybox: Arc<Texture>) -> Self {
+        Environment {
+            skybox,
+            sky: None,
+            night_skybox: None,
+            daylight: 1.0,
+            ambient: AMBIENT_LIGHT_COLOR * AMBIENT_INTENSITY,
+            emission: 1.0,
+            sun: None,
+        }
+    }
+
+    // Color del cielo en la dirección de un rayo que no choca con nada
+    pub fn background(&self, direction: &Vec3) -> Color {
+        let direction = direction.normalize();
+        let mut color = self.sky(&direction);
+
+        if let Some((sun_direction, sun_color)) = &self.sun {
+            // Los discos solo se ven sobre el horizonte; la isla flota y no tapa el cielo de abajo
+            let cos_angle = direction.dot(sun_direction);
+            if cos_angle > SUN_DISC && sun_direction.y > 0.0 {
+                color = color + *sun_color * SUN_RADIANCE;
+            } else if -cos_angle > MOON_DISC && sun_direction.y < 0.0 {
+                color = color + Color::new(0.8, 0.85, 1.0) * MOON_RADIANCE;
+            }
+        }
+
+        color
+    }
+
+    // El cielo sin los discos del sol y la luna, que ya iluminan como luces direccionales
+    pub fn sky(&self, direction: &Vec3) -> Color {
+        let direction = direction.normalize();
+
+        let color = match &self.sky {
+            Some(sky) => sky.radiance(&direction),
+            None => get_skybox_color(&direction, &self.skybox),
+        };
+        match &self.night_skybox {
+            Some(night_skybox) => {
+                let night = get_skybox_color(&direction, night_skybox);
+                color * self.daylight + night * (1.0 - self.daylight)
+            }
+            None => color,
+        }
+    }
+}
+
+fn get_skybox_color(direction: &Vec3, skybox_texture: &Texture) -> Color {
+    let u = 0.5 + direction.x.atan2(direction.z) / (2.0 * PI);
+    let v = 0.5 - (direction.y.asin() / PI);
+
+    let u = u.fract();
+    let v = v.fract();
+
+    let tex_width = skybox_texture.width() as f32;
+    let tex_height = skybox_texture.height() as f32;
+    let tex_x = (u * tex_width).floor() as usize;
+    let tex_y = (v * tex_height).floor() as usize;
+
+    skybox_texture.get_color(tex_x, tex_y)
+}
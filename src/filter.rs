@@ -0,0 +1,69 @@
+use crate::cycle;
+use clap::ValueEnum;
+use std::fmt;
+
+// Filtro de reconstrucción: cuánto pesa en un píxel una muestra a (dx, dy) píxeles de su centro
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Filter {
+    Box,
+    Tent,
+    Gaussian,
+    // Mitchell-Netravali con B = C = 1/3; sus lóbulos negativos dan bordes más nítidos
+    Mitchell,
+}
+
+const GAUSSIAN_ALPHA: f32 = 2.0;
+
+impl Filter {
+    // Medio ancho del soporte, en píxeles
+    pub fn radius(self) -> f32 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian => 1.5,
+            Filter::Mitchell => 2.0,
+        }
+    }
+
+    pub fn weight(self, dx: f32, dy: f32) -> f32 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    fn weight_1d(self, x: f32) -> f32 {
+        let x = x.abs();
+        let radius = self.radius();
+        if x > radius {
+            return 0.0;
+        }
+        match self {
+            Filter::Box => 1.0,
+            Filter::Tent => 1.0 - x,
+            // Se resta el valor en el borde para que llegue a 0 sin un salto
+            Filter::Gaussian => {
+                (-GAUSSIAN_ALPHA * x * x).exp() - (-GAUSSIAN_ALPHA * radius * radius).exp()
+            }
+            Filter::Mitchell => mitchell(x, 1.0 / 3.0, 1.0 / 3.0),
+        }
+    }
+}
+
+// Polinomio cúbico por partes de Mitchell y Netravali, con soporte [0, 2]
+fn mitchell(x: f32, b: f32, c: f32) -> f32 {
+    let value = if x < 1.0 {
+        (12.0 - 9.0 * b - 6.0 * c) * x * x * x
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b)
+    } else {
+        (-b - 6.0 * c) * x * x * x
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c)
+    };
+    value / 6.0
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        cycle::write_name(self, f)
+    }
+}
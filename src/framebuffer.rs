@@ -1,7 +1,20 @@
+use crate::color::Color;
+use crate::tonemap::ToneMapping;
+use image::{ImageResult, Rgb, Rgb32FImage, RgbImage};
+use std::path::Path;
+
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<u32>,
+    // Radiancia lineal de cada píxel antes de convertirla a 8 bits
+    pub radiance: Vec<Color>,
+    // Suma de todas las muestras (ponderadas por el filtro) desde el último reinicio y suma de
+    // los pesos; `radiance` es su promedio
+    accumulation: Vec<Color>,
+    weights: Vec<f32>,
+    pub accumulated_samples: u32,
+    pub tone_mapping: ToneMapping,
     background_color: u32,
     current_color: u32,
 }
@@ -12,6 +25,11 @@ impl Framebuffer {
             width,
             height,
             buffer: vec![0; width * height],
+            radiance: vec![Color::black(); width * height],
+            accumulation: vec![Color::black(); width * height],
+            weights: vec![0.0; width * height],
+            accumulated_samples: 0,
+            tone_mapping: ToneMapping::default(),
             background_color: 0x000000,
             current_color: 0xFFFFFF,
         }
@@ -36,4 +54,69 @@ impl Framebuffer {
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
     }
+
+    // Descarta las muestras acumuladas, p. ej. cuando se mueve la cámara
+    pub fn reset_accumulation(&mut self) {
+        self.accumulation.fill(Color::black());
+        self.weights.fill(0.0);
+        self.accumulated_samples = 0;
+    }
+
+    // Suma `samples` muestras más por píxel (`sums` trae, para cada uno, la suma ponderada de
+    // las muestras y la suma de los pesos) y actualiza el promedio en `radiance`
+    pub fn accumulate(&mut self, sums: &[(Color, f32)], samples: u32) {
+        self.accumulated_samples += samples;
+        for (((total, weight), radiance), &(sum, sum_weight)) in self
+            .accumulation
+            .iter_mut()
+            .zip(self.weights.iter_mut())
+            .zip(self.radiance.iter_mut())
+            .zip(sums)
+        {
+            *total = *total + sum;
+            *weight += sum_weight;
+            // Con los lóbulos negativos de Mitchell la suma de pesos podría quedar en 0
+            *radiance = if *weight > 0.0 {
+                *total * (1.0 / *weight)
+            } else {
+                Color::black()
+            };
+        }
+    }
+
+    // Aplica la exposición y el tone mapping a la radiancia y la convierte a sRGB de 8 bits en
+    // `buffer`. Se puede volver a llamar sin renderizar al cambiar `tone_mapping`.
+    pub fn resolve(&mut self) {
+        let scale = self.tone_mapping.scale(&self.radiance);
+        for (pixel, color) in self.buffer.iter_mut().zip(&self.radiance) {
+            *pixel = self.tone_mapping.apply(*color, scale).to_hex();
+        }
+    }
+
+    // El formato sale de la extensión; en EXR se guarda la radiancia lineal sin recortar
+    pub fn save(&self, path: &Path) -> ImageResult<()> {
+        let width = self.width as u32;
+        let height = self.height as u32;
+
+        let is_exr = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("exr"));
+        if is_exr {
+            let image = Rgb32FImage::from_fn(width, height, |x, y| {
+                let color = self.radiance[y as usize * self.width + x as usize];
+                Rgb([color.r, color.g, color.b])
+            });
+            image.save(path)
+        } else {
+            let image = RgbImage::from_fn(width, height, |x, y| {
+                let pixel = self.buffer[y as usize * self.width + x as usize];
+                Rgb([
+                    ((pixel >> 16) & 0xFF) as u8,
+                    ((pixel >> 8) & 0xFF) as u8,
+                    (pixel & 0xFF) as u8,
+                ])
+            });
+            image.save(path)
+        }
+    }
 }
@@ -0,0 +1,46 @@
+pub mod aabb;
+pub mod atlas;
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod cube;
+pub mod cycle;
+pub mod day_cycle;
+pub mod differential;
+pub mod environment;
+pub mod filter;
+pub mod framebuffer;
+pub mod light;
+pub mod material;
+pub mod microfacet;
+pub mod path_tracer;
+pub mod ray_intersect;
+pub mod renderer;
+pub mod sampling;
+pub mod scene;
+pub mod sky;
+pub mod sphere;
+pub mod texture;
+pub mod tonemap;
+pub mod voxel;
+pub mod world;
+
+pub use bvh::Bvh;
+pub use camera::Camera;
+pub use color::Color;
+pub use cube::Cube;
+pub use day_cycle::DayCycle;
+pub use environment::Environment;
+pub use filter::Filter;
+pub use framebuffer::Framebuffer;
+pub use light::Light;
+pub use material::Material;
+pub use ray_intersect::{Intersect, Object, RayIntersect};
+pub use renderer::{cast_ray, render, Integrator, RenderOptions};
+pub use sampling::SamplePattern;
+pub use scene::{load_scene, Scene, SceneError};
+pub use sky::Sky;
+pub use sphere::Sphere;
+pub use texture::{Texture, TextureFilter};
+pub use tonemap::{Operator, ToneMapping};
+pub use world::World;
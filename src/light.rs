@@ -1,18 +1,401 @@
 use crate::color::Color;
 use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
 
-pub struct Light {
-    pub position: Vec3,
+#[derive(Debug, Clone)]
+pub enum Light {
+    // Luz puntual; se atenúa con el cuadrado de la distancia
+    Point {
+        position: Vec3,
+        color: Color,
+        intensity: f32,
+    },
+    // Luz muy lejana (el sol, la luna): rayos paralelos y sin atenuación. `direction` apunta
+    // hacia donde viaja la luz.
+    Directional {
+        direction: Vec3,
+        color: Color,
+        intensity: f32,
+    },
+    // Cono de luz con medio ángulo `angle` (en radianes); `blend` es la fracción del cono en la
+    // que la luz se desvanece hacia el borde
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        angle: f32,
+        blend: f32,
+        color: Color,
+        intensity: f32,
+    },
+    // Superficie que emite luz, como un bloque luminoso. Se muestrea en `samples` puntos para
+    // que las sombras tengan penumbra.
+    Area {
+        shape: AreaShape,
+        color: Color,
+        intensity: f32,
+        samples: u32,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum AreaShape {
+    // Paralelogramo con una esquina y dos lados; emite hacia el lado de `edge_u × edge_v`
+    Rect {
+        corner: Vec3,
+        edge_u: Vec3,
+        edge_v: Vec3,
+    },
+    // Caja que emite por todas sus caras
+    Box {
+        min: Vec3,
+        max: Vec3,
+    },
+    Sphere {
+        center: Vec3,
+        radius: f32,
+    },
+}
+
+// Lo que le llega a un punto desde una muestra de la luz
+pub struct LightSample {
+    // Dirección (normalizada) desde el punto hacia la luz
+    pub direction: Vec3,
+    // Distancia hasta la muestra; infinita para luces direccionales
+    pub distance: f32,
+    // Color por intensidad, ya atenuado por la distancia, el cono o el ángulo de la superficie.
+    // Es lo que refleja una superficie blanca mate vista de frente (la irradiancia entre π).
     pub color: Color,
-    pub intensity: f32,
+    // Densidad de la muestra en ángulo sólido, para combinar con el muestreo del material; None
+    // en las luces que salen de un solo punto o dirección
+    pub pdf: Option<f32>,
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
 }
 
 impl Light {
     pub fn new(position: Vec3, color: Color, intensity: f32) -> Self {
-        Light {
+        Light::Point {
             position,
             color,
             intensity,
         }
     }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Light::Point { color, .. }
+            | Light::Directional { color, .. }
+            | Light::Spot { color, .. }
+            | Light::Area { color, .. } => *color,
+        }
+    }
+
+    pub fn intensity(&self) -> f32 {
+        match self {
+            Light::Point { intensity, .. }
+            | Light::Directional { intensity, .. }
+            | Light::Spot { intensity, .. }
+            | Light::Area { intensity, .. } => *intensity,
+        }
+    }
+
+    // Lado de la cuadrícula de muestras: las luces de área usan el cuadrado más cercano a
+    // `samples`; las demás salen de un solo punto
+    pub fn sample_grid(&self) -> u32 {
+        match self {
+            Light::Area { samples, .. } => ((*samples as f32).sqrt().round() as u32).max(1),
+            _ => 1,
+        }
+    }
+
+    // La misma luz con la intensidad multiplicada por `factor`
+    pub fn scaled(&self, factor: f32) -> Light {
+        let mut light = self.clone();
+        match &mut light {
+            Light::Point { intensity, .. }
+            | Light::Directional { intensity, .. }
+            | Light::Spot { intensity, .. }
+            | Light::Area { intensity, .. } => *intensity *= factor,
+        }
+        light
+    }
+
+    // Muestra de la luz vista desde `point`. `u` y `v` en [0, 1) eligen el punto de una luz de
+    // área; (0.5, 0.5) es su centro. None si el punto no recibe luz (fuera del cono de un foco o
+    // detrás de un panel).
+    pub fn sample(&self, point: &Vec3, u: f32, v: f32) -> Option<LightSample> {
+        match self {
+            Light::Point {
+                position,
+                color,
+                intensity,
+            } => {
+                let to_light = position - point;
+                let distance = to_light.magnitude();
+                Some(LightSample {
+                    direction: to_light / distance,
+                    distance,
+                    color: *color * (intensity / (distance * distance)),
+                    pdf: None,
+                })
+            }
+            Light::Directional {
+                direction,
+                color,
+                intensity,
+            } => Some(LightSample {
+                direction: -direction.normalize(),
+                distance: f32::INFINITY,
+                color: *color * *intensity,
+                pdf: None,
+            }),
+            Light::Spot {
+                position,
+                direction,
+                angle,
+                blend,
+                color,
+                intensity,
+            } => {
+                let to_light = position - point;
+                let distance = to_light.magnitude();
+                let light_dir = to_light / distance;
+
+                let cos_angle = (-light_dir).dot(&direction.normalize());
+                let cos_outer = angle.cos();
+                let cos_inner = (angle * (1.0 - blend)).cos();
+                let cone = smoothstep(cos_outer, cos_inner, cos_angle);
+                if cone <= 0.0 {
+                    return None;
+                }
+
+                Some(LightSample {
+                    direction: light_dir,
+                    distance,
+                    color: *color * (intensity * cone / (distance * distance)),
+                    pdf: None,
+                })
+            }
+            Light::Area {
+                shape,
+                color,
+                intensity,
+                ..
+            } => {
+                let (sample_point, normal, area_pdf) = shape.sample(point, u, v)?;
+                let to_light = sample_point - point;
+                let distance = to_light.magnitude();
+                let light_dir = to_light / distance;
+
+                // Emisor lambertiano: brilla menos visto de canto
+                let cos_light = normal.dot(&-light_dir);
+                if cos_light <= 0.0 {
+                    return None;
+                }
+
+                // `color * intensity` es la radiancia de la superficie
+                let pdf = area_pdf * distance * distance / cos_light;
+                Some(LightSample {
+                    direction: light_dir,
+                    distance,
+                    color: *color * (intensity / (PI * pdf)),
+                    pdf: Some(pdf),
+                })
+            }
+        }
+    }
+
+    // Si `hit` está sobre la superficie de esta luz de área
+    pub fn emits_from(&self, hit: &Vec3) -> bool {
+        match self {
+            Light::Area { shape, .. } => shape.contains(hit),
+            _ => false,
+        }
+    }
+
+    // Densidad en ángulo sólido con la que `sample` habría elegido `hit` (con normal `normal`)
+    // visto desde `point`
+    pub fn pdf(&self, point: &Vec3, hit: &Vec3, normal: &Vec3) -> f32 {
+        let Light::Area { shape, .. } = self else {
+            return 0.0;
+        };
+        let to_hit = hit - point;
+        let distance_squared = to_hit.magnitude_squared();
+        let cos_light = normal.dot(&(-to_hit / distance_squared.sqrt())).abs();
+        if cos_light <= 0.0 {
+            return 0.0;
+        }
+        shape.area_pdf(point, hit) * distance_squared / cos_light
+    }
+}
+
+// Margen para decidir si un punto está sobre la superficie de una luz
+const SURFACE_EPSILON: f32 = 1e-3;
+
+impl AreaShape {
+    // Punto de la superficie, su normal y la densidad por unidad de área con que se eligió
+    fn sample(&self, point: &Vec3, u: f32, v: f32) -> Option<(Vec3, Vec3, f32)> {
+        match self {
+            AreaShape::Rect {
+                corner,
+                edge_u,
+                edge_v,
+            } => {
+                let cross = edge_u.cross(edge_v);
+                Some((
+                    corner + edge_u * u + edge_v * v,
+                    cross.normalize(),
+                    1.0 / cross.magnitude(),
+                ))
+            }
+            AreaShape::Box { min, max } => {
+                // Solo las caras que ven al punto; `u` elige la cara según cuánto se ve cada una
+                let faces = box_faces(min, max, point);
+                if faces.is_empty() {
+                    // El punto está dentro de la caja
+                    return None;
+                }
+
+                let total: f32 = faces.iter().map(|&(_, _, weight)| weight).sum();
+                let mut pick = u * total;
+                let mut face = faces[faces.len() - 1];
+                let mut face_u = 0.5;
+                for &candidate in &faces {
+                    if pick < candidate.2 {
+                        face = candidate;
+                        face_u = pick / candidate.2;
+                        break;
+                    }
+                    pick -= candidate.2;
+                }
+
+                let (axis, sign, weight) = face;
+                let size = max - min;
+                let (other_a, other_b) = ((axis + 1) % 3, (axis + 2) % 3);
+                let mut sample = *min;
+                sample[axis] = (min[axis] + max[axis]) * 0.5 + sign * size[axis] * 0.5;
+                sample[other_a] += face_u * size[other_a];
+                sample[other_b] += v * size[other_b];
+
+                let mut normal = Vec3::new(0.0, 0.0, 0.0);
+                normal[axis] = sign;
+                let area = size[other_a] * size[other_b];
+                Some((sample, normal, weight / total / area))
+            }
+            AreaShape::Sphere { center, radius } => {
+                // Un punto del casquete que ve al punto sombreado
+                let to_point = point - center;
+                let distance = to_point.magnitude();
+                if distance <= *radius {
+                    return None;
+                }
+                let w = to_point / distance;
+                let helper = if w.x.abs() > 0.9 {
+                    Vec3::new(0.0, 1.0, 0.0)
+                } else {
+                    Vec3::new(1.0, 0.0, 0.0)
+                };
+                let tangent = w.cross(&helper).normalize();
+                let bitangent = w.cross(&tangent);
+
+                // Uniforme en el casquete visible, cuyo borde está en cos θ = r / d
+                let cos_max = radius / distance;
+                let cos_theta = 1.0 - u * (1.0 - cos_max);
+                let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+                let phi = 2.0 * PI * v;
+                let normal = (tangent * (sin_theta * phi.cos())
+                    + bitangent * (sin_theta * phi.sin())
+                    + w * cos_theta)
+                    .normalize();
+                Some((
+                    center + normal * *radius,
+                    normal,
+                    sphere_cap_pdf(*radius, distance),
+                ))
+            }
+        }
+    }
+
+    fn contains(&self, hit: &Vec3) -> bool {
+        match self {
+            AreaShape::Rect {
+                corner,
+                edge_u,
+                edge_v,
+            } => {
+                let normal = edge_u.cross(edge_v).normalize();
+                let offset = hit - corner;
+                let u = offset.dot(edge_u) / edge_u.magnitude_squared();
+                let v = offset.dot(edge_v) / edge_v.magnitude_squared();
+                offset.dot(&normal).abs() < SURFACE_EPSILON
+                    && (0.0..=1.0).contains(&u)
+                    && (0.0..=1.0).contains(&v)
+            }
+            AreaShape::Box { min, max } => (0..3).all(|axis| {
+                hit[axis] > min[axis] - SURFACE_EPSILON && hit[axis] < max[axis] + SURFACE_EPSILON
+            }),
+            AreaShape::Sphere { center, radius } => {
+                ((hit - center).magnitude() - radius).abs() < SURFACE_EPSILON
+            }
+        }
+    }
+
+    // Densidad por unidad de área con que `sample` elige `hit` visto desde `point`
+    fn area_pdf(&self, point: &Vec3, hit: &Vec3) -> f32 {
+        match self {
+            AreaShape::Rect { edge_u, edge_v, .. } => 1.0 / edge_u.cross(edge_v).magnitude(),
+            AreaShape::Box { min, max } => {
+                let faces = box_faces(min, max, point);
+                let total: f32 = faces.iter().map(|&(_, _, weight)| weight).sum();
+                let size = max - min;
+                // La cara de `hit` es la que está en el borde de la caja
+                faces
+                    .iter()
+                    .find(|&&(axis, sign, _)| {
+                        let border = if sign > 0.0 { max[axis] } else { min[axis] };
+                        (hit[axis] - border).abs() < SURFACE_EPSILON
+                    })
+                    .map_or(0.0, |&(axis, _, weight)| {
+                        let area = size[(axis + 1) % 3] * size[(axis + 2) % 3];
+                        weight / total / area
+                    })
+            }
+            AreaShape::Sphere { center, radius } => {
+                let distance = (point - center).magnitude();
+                if distance <= *radius {
+                    0.0
+                } else {
+                    sphere_cap_pdf(*radius, distance)
+                }
+            }
+        }
+    }
+}
+
+// Caras de la caja que ve `point`, como (eje, signo, peso). El peso es el área proyectada de
+// la cara vista desde el punto.
+fn box_faces(min: &Vec3, max: &Vec3, point: &Vec3) -> Vec<(usize, f32, f32)> {
+    let size = max - min;
+    let center = (min + max) * 0.5;
+    let to_point = point - center;
+    let distance = to_point.magnitude();
+    let mut faces = Vec::with_capacity(3);
+    for axis in 0..3 {
+        let (other_a, other_b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let area = size[other_a] * size[other_b];
+        let offset = to_point[axis];
+        if offset.abs() > size[axis] * 0.5 && area > 0.0 {
+            faces.push((axis, offset.signum(), area * offset.abs() / distance));
+        }
+    }
+    faces
+}
+
+// Uniforme sobre el casquete visible desde una distancia `distance` del centro
+fn sphere_cap_pdf(radius: f32, distance: f32) -> f32 {
+    let cos_max = radius / distance;
+    1.0 / (2.0 * PI * radius * radius * (1.0 - cos_max))
 }
@@ -1,505 +1,102 @@
-use core::f32;
-use minifb::{Key, Window, WindowOptions};
+use clap::Parser;
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
 use nalgebra_glm::Vec3;
-use std::time::Duration;
-
+use raytracing::cycle::Cycle;
+use raytracing::scene::SceneWatcher;
+use raytracing::voxel::VoxelGrid;
+use raytracing::{
+    load_scene, render, Bvh, Filter, Framebuffer, Integrator, Object, RenderOptions, SamplePattern,
+    Scene, TextureFilter,
+};
 use std::f32::consts::PI;
-use std::f32::INFINITY;
-
-mod framebuffer;
-use framebuffer::Framebuffer;
-
-mod ray_intersect;
-use ray_intersect::{Intersect, RayIntersect};
-
-mod color;
-use color::Color;
-
-mod camera;
-use camera::Camera;
-
-mod material;
-use material::Material;
-
-mod light;
-use light::Light;
-
-mod texture;
-use texture::Texture;
-
-mod cube;
-use cube::Cube;
-
-use rayon::prelude::*;
-
-mod block_textures;
-use block_textures::Textures;
-
-const BIAS: f32 = 0.001;
-const AMBIENT_LIGHT_COLOR: Color = Color::new(25, 25, 25);
-const AMBIENT_INTENSITY: f32 = 0.3;
-
-fn offset_point(intersect: &Intersect, _direction: &Vec3) -> Vec3 {
-    let offset = intersect.normal * BIAS;
-    intersect.point + offset
-}
-
-fn reflect(incident: &Vec3, normal: &Vec3) -> Vec3 {
-    incident - 2.0 * incident.dot(normal) * normal
-}
-
-fn refract(incident: &Vec3, normal: &Vec3, eta_t: f32) -> Vec3 {
-    let cosi = -incident.dot(normal).max(-1.0).min(1.0);
-
-    let n_cosi: f32;
-    let eta: f32;
-    let n_normal: Vec3;
-
-    if cosi < 0.0 {
-        n_cosi = -cosi;
-        eta = 1.0 / eta_t;
-        n_normal = -normal;
-    } else {
-        n_cosi = cosi;
-        eta = eta_t;
-        n_normal = *normal;
-    }
-
-    let k = 1.0 - eta * eta * (1.0 - n_cosi * n_cosi);
-
-    if k > 0.0 {
-        reflect(incident, &n_normal)
-    } else {
-        incident * eta + (eta * n_cosi - k.sqrt()) * n_normal
-    }
-}
-
-fn cast_shadow(intersect: &Intersect, light: &Light, objects: &[Cube]) -> f32 {
-    let light_dir = (light.position - intersect.point).normalize();
-    let light_distance = (light.position - intersect.point).magnitude();
-    let shadow_ray_origin = offset_point(intersect, &light_dir);
-
-    for object in objects {
-        let shadow_intersect = object.ray_intersect(&shadow_ray_origin, &light_dir);
-        if shadow_intersect.is_intersecting && shadow_intersect.distance < light_distance {
-            return 0.5
-                - (shadow_intersect.distance / light_distance)
-                    .powf(2.0)
-                    .min(1.0);
-        }
-    }
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-    0.0
-}
+const MAX_SAMPLES: u32 = 1024;
 
-fn fresnel_schlick(cos_theta: f32, ior: f32) -> f32 {
-    let r0 = (1.0 - ior) / (1.0 + ior);
-    let r0 = r0 * r0;
-    r0 + (1.0 - r0) * (1.0 - cos_theta).powi(5)
+// La misma escena como mundo por chunks, cuadrícula densa y lista de cubos, para comparar
+struct SceneObjects {
+    world: Bvh<Object>,
+    voxel_grid: Bvh<Object>,
+    cubes: Bvh<Object>,
 }
 
-fn get_skybox_color(direction: &Vec3, skybox_texture: &Texture) -> Color {
-    let normalized_dir = direction.normalize();
-    let u = 0.5 + normalized_dir.x.atan2(normalized_dir.z) / (2.0 * PI);
-    let v = 0.5 - (normalized_dir.y.asin() / PI);
-
-    let u = u.fract();
-    let v = v.fract();
-
-    let tex_width = skybox_texture.width() as f32;
-    let tex_height = skybox_texture.height() as f32;
-    let tex_x = (u * tex_width).floor() as usize;
-    let tex_y = (v * tex_height).floor() as usize;
+impl SceneObjects {
+    fn new(scene: &Scene) -> Self {
+        let cubes = scene.world.to_cubes();
 
-    skybox_texture.get_color(tex_x, tex_y)
-}
-
-pub fn cast_ray(
-    ray_origin: &Vec3,
-    ray_direction: &Vec3,
-    objects: &[Cube],
-    lights: &[Light],
-    depth: u32,
-    textures: &Textures,
-) -> Color {
-    if depth >= 3 {
-        return get_skybox_color(ray_direction, &textures.skybox_texture);
-    }
+        let mut voxel_grid: Vec<Object> = vec![Arc::new(VoxelGrid::from_cubes(&cubes))];
+        voxel_grid.extend(scene.shapes.iter().cloned());
 
-    let mut intersect = Intersect::empty();
-    let mut zbuffer = INFINITY;
+        let mut cube_list: Vec<Object> = cubes
+            .into_iter()
+            .map(|cube| Arc::new(cube) as Object)
+            .collect();
+        cube_list.extend(scene.shapes.iter().cloned());
 
-    for object in objects {
-        let i = object.ray_intersect(ray_origin, ray_direction);
-        if i.is_intersecting && i.distance < zbuffer {
-            zbuffer = i.distance;
-            intersect = i;
+        SceneObjects {
+            world: Bvh::new(scene.objects()),
+            voxel_grid: Bvh::new(voxel_grid),
+            cubes: Bvh::new(cube_list),
         }
     }
 
-    if !intersect.is_intersecting {
-        return get_skybox_color(ray_direction, &textures.skybox_texture);
+    fn is_linear_scan(&self) -> bool {
+        self.cubes.is_linear_scan()
     }
 
-    let ambient_light = AMBIENT_LIGHT_COLOR * AMBIENT_INTENSITY;
-    let mut color = ambient_light;
-
-    if intersect.material.emission.r > 0
-        || intersect.material.emission.g > 0
-        || intersect.material.emission.b > 0
-    {
-        color = color + intersect.material.emission;
-    }
-
-    for light in lights {
-        let light_effect = calculate_lighting(
-            &intersect,
-            light,
-            ray_direction,
-            objects,
-            depth,
-            textures,
-            lights,
-        );
-
-        color = color + light_effect;
+    // Las tres estructuras comparten el modo, así V compara con o sin BVH según B
+    fn set_linear_scan(&mut self, enabled: bool) {
+        self.world.set_linear_scan(enabled);
+        self.voxel_grid.set_linear_scan(enabled);
+        self.cubes.set_linear_scan(enabled);
     }
-
-    color
-}
-fn calculate_lighting(
-    intersect: &Intersect,
-    light: &Light,
-    ray_direction: &Vec3,
-    objects: &[Cube],
-    depth: u32,
-    textures: &Textures,
-    lights: &[Light],
-) -> Color {
-    let mut total_light = Color::new(0, 0, 0);
-
-    let light_dir = (light.position - intersect.point).normalize();
-    let view_dir = (-ray_direction).normalize();
-    let reflect_dir = reflect(ray_direction, &intersect.normal).normalize();
-    let cos_theta = -ray_direction.dot(&intersect.normal).max(-1.0).min(1.0);
-
-    let shadow_intensity = cast_shadow(intersect, light, objects);
-    let light_intensity = light.intensity * (1.0 - shadow_intensity);
-
-    let diffuse_intensity = intersect.normal.dot(&light_dir).max(0.0);
-    let diffuse_color = intersect
-        .material
-        .get_diffuse_color(intersect.u, intersect.v);
-    let diffuse =
-        diffuse_color * intersect.material.albedo[0] * diffuse_intensity * light_intensity;
-
-    let specular_intensity = view_dir
-        .dot(&reflect_dir)
-        .max(0.0)
-        .powf(intersect.material.specular);
-    let specular =
-        light.color * intersect.material.albedo[1] * specular_intensity * light_intensity;
-
-    // Efecto Fresnel y reflexión
-    let fresnel_effect = fresnel_schlick(cos_theta.abs(), intersect.material.refractive_index);
-    let reflect_color = if intersect.material.albedo[2] > 0.0 {
-        let reflect_dir = reflect(ray_direction, &intersect.normal).normalize();
-        let reflect_origin = offset_point(intersect, &reflect_dir);
-        cast_ray(
-            &reflect_origin,
-            &reflect_dir,
-            objects,
-            lights,
-            depth + 1,
-            textures,
-        ) * fresnel_effect
-    } else {
-        Color::black()
-    };
-
-    let refract_color = if intersect.material.albedo[3] > 0.0 {
-        let refract_dir = refract(
-            ray_direction,
-            &intersect.normal,
-            intersect.material.refractive_index,
-        );
-        let refract_origin = offset_point(intersect, &refract_dir);
-        cast_ray(
-            &refract_origin,
-            &refract_dir,
-            objects,
-            lights,
-            depth + 1,
-            textures,
-        ) * (1.0 - fresnel_effect)
-    } else {
-        Color::black()
-    };
-
-    total_light = total_light + diffuse + specular;
-
-    if intersect.material.emission.r > 0
-        || intersect.material.emission.g > 0
-        || intersect.material.emission.b > 0
-    {
-        total_light = total_light + intersect.material.emission;
-    }
-
-    total_light = total_light + reflect_color + refract_color;
-
-    total_light
 }
 
-pub fn render(
-    framebuffer: &mut Framebuffer,
-    objects: &[Cube],
-    camera: &Camera,
-    lights: &[Light],
-    textures: &Textures,
-) {
-    let width = framebuffer.width as f32;
-    let height = framebuffer.height as f32;
-    let aspect_ratio = width / height;
-    let fov = PI / 3.0;
-    let perspective_scale = (fov / 2.0).tan();
-
-    let pixels: Vec<_> = (0..framebuffer.height)
-        .flat_map(|y| (0..framebuffer.width).map(move |x| (x, y)))
-        .collect();
-
-    let pixel_colors: Vec<(usize, usize, u32)> = pixels
-        .par_iter()
-        .map(|&(x, y)| {
-            let screen_x = (2.0 * x as f32) / width - 1.0;
-            let screen_y = -(2.0 * y as f32) / height + 1.0;
-            let screen_x = screen_x * aspect_ratio * perspective_scale;
-            let screen_y = screen_y * perspective_scale;
-            let ray_direction = Vec3::new(screen_x, screen_y, -1.0).normalize();
-            let rotated_direction = camera.basis_change(&ray_direction);
-            let pixel_color = cast_ray(
-                &camera.eye,
-                &rotated_direction,
-                objects,
-                lights,
-                0,
-                textures,
-            );
-            (x, y, pixel_color.to_hex())
-        })
-        .collect();
-
-    for (x, y, color) in pixel_colors {
-        framebuffer.set_current_color(color);
-        framebuffer.point(x, y);
-    }
-}
+#[derive(Parser)]
+#[command(about = "Visor interactivo del diorama de Minecraft")]
+struct Args {
+    /// Archivo de escena
+    #[arg(default_value = "scenes/diorama.toml")]
+    scene: PathBuf,
 
-fn generate_grid(
+    /// Ancho de la ventana en píxeles
+    #[arg(long, default_value_t = 800)]
     width: usize,
-    height: usize,
-    start_x: f32,
-    start_y: f32,
-    start_z: f32,
-    material: Material,
-) -> Vec<Cube> {
-    let mut objects = Vec::new();
-    for y in 0..height {
-        for x in 0..width {
-            objects.push(Cube {
-                min: Vec3::new(start_x + x as f32, start_y, start_z + y as f32),
-                max: Vec3::new(
-                    start_x + (x + 1) as f32,
-                    start_y + 1.0,
-                    start_z + (y + 1) as f32,
-                ),
-                material: material.clone(),
-            });
-        }
-    }
-    objects
-}
 
-fn create_nether_portal(textures: &Textures) -> Vec<Cube> {
-    let mut objects = Vec::new();
-
-    objects.push(Cube {
-        min: Vec3::new(3.0, 2.0, 6.0),
-        max: Vec3::new(4.0, 3.0, 7.0),
-        material: textures.oak_planks_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(4.0, 2.0, 6.0),
-        max: Vec3::new(5.0, 3.0, 7.0),
-        material: textures.obsidian_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(5.0, 2.0, 6.0),
-        max: Vec3::new(6.0, 3.0, 7.0),
-        material: textures.obsidian_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(6.0, 2.0, 6.0),
-        max: Vec3::new(7.0, 3.0, 7.0),
-        material: textures.oak_planks_material.clone(),
-    });
-
-    objects.push(Cube {
-        min: Vec3::new(3.0, 3.0, 6.0),
-        max: Vec3::new(4.0, 4.0, 7.0),
-        material: textures.obsidian_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(6.0, 3.0, 6.0),
-        max: Vec3::new(7.0, 4.0, 7.0),
-        material: textures.obsidian_material.clone(),
-    });
-
-    objects.push(Cube {
-        min: Vec3::new(3.0, 4.0, 6.0),
-        max: Vec3::new(4.0, 5.0, 7.0),
-        material: textures.obsidian_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(6.0, 4.0, 6.0),
-        max: Vec3::new(7.0, 5.0, 7.0),
-        material: textures.obsidian_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(6.0, 5.0, 6.0),
-        max: Vec3::new(7.0, 6.0, 7.0),
-        material: textures.oak_planks_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(5.0, 5.0, 6.0),
-        max: Vec3::new(6.0, 6.0, 7.0),
-        material: textures.obsidian_material.clone(),
-    });
-
-    objects
-}
-
-fn create_player_tools(textures: &Textures) -> Vec<Cube> {
-    let mut objects = Vec::new();
-
-    let wood_row = generate_grid(1, 7, 7.0, 1.0, 0.0, textures.oak_planks_material.clone());
-    objects.extend(wood_row);
-
-    objects.push(Cube {
-        min: Vec3::new(7.0, 2.0, 2.0),
-        max: Vec3::new(8.0, 3.0, 3.0),
-        material: textures.smoker_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(7.0, 2.0, 3.0),
-        max: Vec3::new(8.0, 3.0, 4.0),
-        material: textures.crafting_table_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(7.0, 2.0, 4.0),
-        max: Vec3::new(8.0, 3.0, 5.0),
-        material: textures.bookshelf_material.clone(),
-    });
-
-    objects
-}
-
-fn create_cactus(textures: &Textures) -> Vec<Cube> {
-    let mut objects = Vec::new();
-
-    objects.push(Cube {
-        min: Vec3::new(2.0, 2.0, 2.0),
-        max: Vec3::new(3.0, 3.0, 3.0),
-        material: textures.sand_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(2.0, 3.0, 2.0),
-        max: Vec3::new(3.0, 4.0, 3.0),
-        material: textures.cactus_material.clone(),
-    });
-
-    objects.push(Cube {
-        min: Vec3::new(3.0, 2.0, 1.0),
-        max: Vec3::new(4.0, 3.0, 2.0),
-        material: textures.sand_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(3.0, 3.0, 1.0),
-        max: Vec3::new(4.0, 4.0, 2.0),
-        material: textures.cactus_material.clone(),
-    });
-
-    objects
-}
-
-fn create_tree(textures: &Textures) -> Vec<Cube> {
-    let mut objects = Vec::new();
-
-    objects.push(Cube {
-        min: Vec3::new(1.0, 2.0, 5.0),
-        max: Vec3::new(2.0, 3.0, 6.0),
-        material: textures.oak_log_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(1.0, 3.0, 5.0),
-        max: Vec3::new(2.0, 4.0, 6.0),
-        material: textures.oak_log_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(1.0, 4.0, 5.0),
-        max: Vec3::new(2.0, 5.0, 6.0),
-        material: textures.oak_log_material.clone(),
-    });
-    objects.push(Cube {
-        min: Vec3::new(1.0, 5.0, 5.0),
-        max: Vec3::new(2.0, 6.0, 6.0),
-        material: textures.oak_log_material.clone(),
-    });
-
-    objects
-}
-
-fn create_shroomlights(textures: &Textures) -> Vec<Cube> {
-    let mut objects = Vec::new();
+    /// Alto de la ventana en píxeles
+    #[arg(long, default_value_t = 600)]
+    height: usize,
 
-    objects.push(Cube {
-        min: Vec3::new(6.0, 2.0, 0.0),
-        max: Vec3::new(7.0, 3.0, 1.0),
-        material: textures.shroomlight_material.clone(),
-    });
+    /// Muestras por píxel en cada cuadro
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    samples: u32,
 
-    objects.push(Cube {
-        min: Vec3::new(0.0, 2.0, 0.0),
-        max: Vec3::new(1.0, 3.0, 1.0),
-        material: textures.shroomlight_material.clone(),
-    });
+    /// Filtro de reconstrucción: box, tent, gaussian o mitchell
+    #[arg(long, default_value = "box")]
+    filter: Filter,
 
-    objects
+    /// Filtrado de las texturas: nearest, bilinear o trilinear (con mipmaps)
+    #[arg(long, default_value = "trilinear")]
+    textures: TextureFilter,
 }
 
-fn create_shroomlight_lights(_textures: &Textures) -> Vec<Light> {
-    let mut lights = Vec::new();
-    let emission_color = Color::new(240, 197, 101);
-    let intensity = 1.5;
-
-    lights.push(Light::new(
-        Vec3::new(6.5, 2.5, 0.5),
-        emission_color,
-        intensity,
-    ));
-    lights.push(Light::new(
-        Vec3::new(0.5, 2.5, 0.5),
-        emission_color,
-        intensity,
-    ));
-
-    lights
-}
 fn main() {
-    let window_width = 800;
-    let window_height = 600;
+    let args = Args::parse();
+
+    let mut scene = match load_scene(&args.scene) {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("Failed to load scene: {e}");
+            std::process::exit(1);
+        }
+    };
+    let window_width = args.width;
+    let window_height = args.height;
 
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
+    let framebuffer_width = args.width;
+    let framebuffer_height = args.height;
 
     let frame_delay = Duration::from_millis(0);
 
@@ -518,51 +115,70 @@ fn main() {
 
     framebuffer.set_background_color(0x333355);
 
-    let textures = Textures::new();
-
-    let mut objects = generate_grid(7, 7, 0.0, 1.0, 0.0, textures.grass_material.clone());
-    let mut dirt = generate_grid(7, 7, 0.0, 0.0, 0.0, textures.dirt_material.clone());
-    objects.append(&mut dirt);
-
-    objects.append(&mut create_nether_portal(&textures));
-    objects.append(&mut create_player_tools(&textures));
-    objects.append(&mut create_cactus(&textures));
-    objects.append(&mut create_tree(&textures));
-    objects.append(&mut create_shroomlights(&textures));
+    let mut watcher = SceneWatcher::new(&scene.files);
+    let mut reload_error: Option<String> = None;
 
-    let mut camera = Camera::new(
-        Vec3::new(-5.0, 5.0, -10.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-    );
-
-    let mut lights = vec![Light::new(
-        Vec3::new(-5.0, 10.0, -10.0),
-        Color::new(255, 255, 255),
-        0.5,
-    )];
-    lights.extend(create_shroomlight_lights(&textures));
+    let mut objects = SceneObjects::new(&scene);
+    let mut accel_mode = 0;
 
     let rotation_speed = PI / 50.0;
     let movement_speed = 0.1;
     let zoom_speed = 0.5;
+    let exposure_step = 0.25;
+    // Reloj de las texturas animadas; se detiene con la pausa
+    let mut animation_time = 0.0;
+    let mut last_frame = Instant::now();
+    let mut time_paused = false;
+    let mut use_sky = true;
+    let mut integrator = Integrator::Whitted;
+    let mut samples = args.samples;
+    let mut filter = args.filter;
+    let mut pattern = SamplePattern::Stratified;
+    let mut texture_filter = args.textures;
+    let mut scene_changed = true;
 
     while window.is_open() {
         if window.is_key_down(Key::Escape) {
             break;
         }
 
+        // Recarga en caliente: se conservan la cámara, la hora y, si falla, la escena anterior
+        if watcher.poll() {
+            match load_scene(&args.scene) {
+                Ok(mut new_scene) => {
+                    let linear = objects.is_linear_scan();
+                    objects = SceneObjects::new(&new_scene);
+                    objects.set_linear_scan(linear);
+                    watcher.watch(&new_scene.files);
+                    if let (Some(new_cycle), Some(cycle)) =
+                        (&mut new_scene.day_cycle, &scene.day_cycle)
+                    {
+                        new_cycle.hour = cycle.hour;
+                        new_cycle.speed = cycle.speed;
+                    }
+                    new_scene.camera = scene.camera;
+                    scene = new_scene;
+                    reload_error = None;
+                    scene_changed = true;
+                }
+                Err(e) => {
+                    eprintln!("Failed to reload scene: {e}");
+                    reload_error = Some(e.to_string());
+                }
+            }
+        }
+
         if window.is_key_down(Key::Left) {
-            camera.orbit(rotation_speed, 0.0);
+            scene.camera.orbit(rotation_speed, 0.0);
         }
         if window.is_key_down(Key::Right) {
-            camera.orbit(-rotation_speed, 0.0);
+            scene.camera.orbit(-rotation_speed, 0.0);
         }
         if window.is_key_down(Key::W) {
-            camera.orbit(0.0, -rotation_speed);
+            scene.camera.orbit(0.0, -rotation_speed);
         }
         if window.is_key_down(Key::S) {
-            camera.orbit(0.0, rotation_speed);
+            scene.camera.orbit(0.0, rotation_speed);
         }
 
         let mut movement = Vec3::new(0.0, 0.0, 0.0);
@@ -579,18 +195,195 @@ fn main() {
             movement.y -= movement_speed;
         }
         if movement.magnitude() > 0.0 {
-            camera.move_center(movement);
+            scene.camera.move_center(movement);
         }
 
         if window.is_key_down(Key::Up) {
-            camera.zoom(zoom_speed);
+            scene.camera.zoom(zoom_speed);
         }
         if window.is_key_down(Key::Down) {
-            camera.zoom(-zoom_speed);
+            scene.camera.zoom(-zoom_speed);
+        }
+
+        // B alterna entre el BVH y el recorrido lineal para comparar tiempos
+        if window.is_key_pressed(Key::B, KeyRepeat::No) {
+            let linear = !objects.is_linear_scan();
+            objects.set_linear_scan(linear);
+        }
+        // V alterna entre el mundo por chunks, la cuadrícula densa y la lista de cubos
+        if window.is_key_pressed(Key::V, KeyRepeat::No) {
+            accel_mode = (accel_mode + 1) % 3;
+            scene_changed = true;
         }
 
+        // Tone mapping: T cambia la curva, +/- la exposición y X la exposición automática
+        if window.is_key_pressed(Key::T, KeyRepeat::No) {
+            framebuffer.tone_mapping.operator = framebuffer.tone_mapping.operator.next();
+        }
+        if window.is_key_pressed(Key::Equal, KeyRepeat::Yes) {
+            framebuffer.tone_mapping.exposure += exposure_step;
+        }
+        if window.is_key_pressed(Key::Minus, KeyRepeat::Yes) {
+            framebuffer.tone_mapping.exposure -= exposure_step;
+        }
+        if window.is_key_pressed(Key::X, KeyRepeat::No) {
+            framebuffer.tone_mapping.auto_exposure = !framebuffer.tone_mapping.auto_exposure;
+        }
+
+        // Ciclo de día: , y . cambian la velocidad del tiempo y P lo pausa (también las
+        // texturas animadas)
+        let elapsed = last_frame.elapsed().as_secs_f32();
+        last_frame = Instant::now();
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            time_paused = !time_paused;
+        }
+        let previous_time = animation_time;
+        if !time_paused {
+            animation_time += elapsed;
+        }
+        if let Some(cycle) = &mut scene.day_cycle {
+            if window.is_key_pressed(Key::Comma, KeyRepeat::No) {
+                cycle.speed /= 2.0;
+            }
+            if window.is_key_pressed(Key::Period, KeyRepeat::No) {
+                cycle.speed *= 2.0;
+            }
+            if !time_paused {
+                let hour = cycle.hour;
+                cycle.advance(elapsed);
+                scene_changed |= cycle.hour != hour;
+            }
+        }
+        // I alterna entre Whitted y path tracing
+        if window.is_key_pressed(Key::I, KeyRepeat::No) {
+            integrator = integrator.next();
+            scene_changed = true;
+        }
+        // Antialiasing: [ y ] cambian las muestras por cuadro, F el filtro y G el reparto
+        if window.is_key_pressed(Key::LeftBracket, KeyRepeat::No) {
+            samples = (samples / 2).max(1);
+        }
+        if window.is_key_pressed(Key::RightBracket, KeyRepeat::No) && samples < MAX_SAMPLES {
+            samples *= 2;
+        }
+        if window.is_key_pressed(Key::F, KeyRepeat::No) {
+            filter = filter.next();
+            scene_changed = true;
+        }
+        if window.is_key_pressed(Key::G, KeyRepeat::No) {
+            pattern = pattern.next();
+            scene_changed = true;
+        }
+        // M cambia el filtrado de las texturas
+        if window.is_key_pressed(Key::M, KeyRepeat::No) {
+            texture_filter = texture_filter.next();
+            scene_changed = true;
+        }
+        // K alterna entre el cielo analítico (si la escena lo tiene) y la textura del skybox
+        if window.is_key_pressed(Key::K, KeyRepeat::No) {
+            use_sky = !use_sky;
+            scene_changed = true;
+        }
+
+        let lights = scene.lights();
+        let mut environment = scene.environment();
+        if !use_sky {
+            environment.sky = None;
+        }
+
+        // Mientras nada cambie, cada cuadro suma muestras a las anteriores y la imagen converge
+        let camera_moved = scene.camera.is_changed();
+        // El tiempo solo descarta lo acumulado si algo visible cambió: un cuadro nuevo de una
+        // animación, una textura que se desplaza o el sol que se mueve
+        let time_changed = scene.changes_between(previous_time, animation_time);
+        if camera_moved || scene_changed || time_changed {
+            framebuffer.reset_accumulation();
+        }
+        scene_changed = false;
+
         framebuffer.clear();
-        render(&mut framebuffer, &objects, &mut camera, &lights, &textures);
+        let options = RenderOptions {
+            samples,
+            time: animation_time,
+            integrator,
+            pattern,
+            filter,
+            texture_filter,
+        };
+        let frame_start = Instant::now();
+        match accel_mode {
+            0 => render(
+                &mut framebuffer,
+                &objects.world,
+                &scene.camera,
+                &lights,
+                &environment,
+                &options,
+            ),
+            1 => render(
+                &mut framebuffer,
+                &objects.voxel_grid,
+                &scene.camera,
+                &lights,
+                &environment,
+                &options,
+            ),
+            _ => render(
+                &mut framebuffer,
+                &objects.cubes,
+                &scene.camera,
+                &lights,
+                &environment,
+                &options,
+            ),
+        }
+        let frame_time = frame_start.elapsed();
+
+        let tone_mapping = framebuffer.tone_mapping;
+        let mut title = format!(
+            "Gráficas - Diorama Minecraft [{} {}: {:.1} ms] [{}, {} spp, {}/cuadro, {} {}, texturas {}] [{} {:+.2} EV{}]",
+            if accel_mode == 0 {
+                "chunks"
+            } else if accel_mode == 1 {
+                "vóxeles"
+            } else {
+                "cubos"
+            },
+            if objects.is_linear_scan() {
+                "lineal"
+            } else {
+                "BVH"
+            },
+            frame_time.as_secs_f32() * 1000.0,
+            integrator,
+            framebuffer.accumulated_samples,
+            samples,
+            pattern,
+            filter,
+            texture_filter,
+            tone_mapping.operator,
+            tone_mapping.exposure,
+            if tone_mapping.auto_exposure {
+                " auto"
+            } else {
+                ""
+            }
+        );
+        if let Some(cycle) = &scene.day_cycle {
+            let minutes = (cycle.hour * 60.0) as u32;
+            title.push_str(&format!(
+                " [{:02}:{:02} x{}{}]",
+                minutes / 60,
+                minutes % 60,
+                cycle.speed,
+                if time_paused { " pausa" } else { "" }
+            ));
+        }
+        if let Some(error) = &reload_error {
+            title.push_str(" - Error: ");
+            title.push_str(error);
+        }
+        window.set_title(&title);
 
         window
             .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
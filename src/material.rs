@@ -1,77 +1,373 @@
 use crate::color::Color;
-use crate::texture::Texture;
+use crate::cube::Face;
+use crate::microfacet::{dielectric_reflectance, Surface};
+use crate::texture::{TextureLookup, TextureView};
+use nalgebra_glm::Vec3;
 use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+// Textura de una cara de un bloque, como en los modelos de bloque de Minecraft
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaceTexture {
+    pub texture: TextureView,
+    pub normal_map: Option<TextureView>,
+    // Rectángulo [x1, y1, x2, y2] de la textura, de 0 a 16 con y hacia abajo como en Minecraft;
+    // si x2 < x1 o y2 < y1 la cara se espeja
+    pub uv: [f32; 4],
+    // Giro de la textura en sentido horario: 0, 90, 180 o 270 grados
+    pub rotation: u32,
+}
+
+impl FaceTexture {
+    pub fn new(texture: TextureView) -> Self {
+        FaceTexture {
+            texture,
+            normal_map: None,
+            uv: [0.0, 0.0, 16.0, 16.0],
+            rotation: 0,
+        }
+    }
+
+    // Qué parte de la textura (en u y en v) cubre la cara
+    fn span(&self) -> (f32, f32) {
+        let [x1, y1, x2, y2] = self.uv;
+        ((x2 - x1).abs() / 16.0, (y2 - y1).abs() / 16.0)
+    }
+
+    // Lleva (u, v) de la cara a la textura, y con ellos las direcciones en las que crecen
+    fn map(&self, u: f32, v: f32, tangent: Vec3, bitangent: Vec3) -> ((f32, f32), (Vec3, Vec3)) {
+        let ((u, v), (tangent, bitangent)) = match self.rotation {
+            90 => ((1.0 - v, u), (-bitangent, tangent)),
+            180 => ((1.0 - u, 1.0 - v), (-tangent, -bitangent)),
+            270 => ((v, 1.0 - u), (bitangent, -tangent)),
+            _ => ((u, v), (tangent, bitangent)),
+        };
+
+        let [x1, y1, x2, y2] = self.uv;
+        let x = x1 + u * (x2 - x1);
+        let y = y1 + (1.0 - v) * (y2 - y1);
+        (
+            (x / 16.0, 1.0 - y / 16.0),
+            (tangent * (x2 - x1).signum(), bitangent * (y2 - y1).signum()),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Material {
+    // Color base: el difuso de los dieléctricos y el reflejo de los metales
     pub diffuse: Color,
-    pub specular: f32,
-    pub albedo: [f32; 4],
+    // 0 es un espejo pulido y 1 un material completamente mate
+    pub roughness: f32,
+    pub metallic: f32,
+    // Fracción de la luz que atraviesa el material en lugar de reflejarse (vidrio, agua)
+    pub transmission: f32,
     pub refractive_index: f32,
     pub emission: Color,
     pub has_texture: bool,
-    pub texture: Option<Arc<Texture>>,
+    pub texture: Option<TextureView>,
+    // Mapa de normales en espacio tangente (RGB = tangente, bitangente, normal)
+    pub normal_map: Option<TextureView>,
+    // Textura de cada cara de un bloque (en el orden de `Face::ALL`); las que no tienen usan
+    // `texture` y `normal_map` en la cara entera
+    pub faces: Option<Arc<[Option<FaceTexture>; 6]>>,
+    // Multiplican a `roughness`, `metallic` y `emission`; de los dos primeros se usa el canal rojo
+    pub roughness_map: Option<TextureView>,
+    pub metallic_map: Option<TextureView>,
+    pub emission_map: Option<TextureView>,
+    // Desplazamiento de las coordenadas UV por segundo (agua, lava...)
+    pub scroll: [f32; 2],
+    // Absorción por unidad de distancia dentro del material (ley de Beer–Lambert)
+    pub absorption: Color,
 }
 
 impl Material {
     pub fn new(
         diffuse: Color,
-        specular: f32,
-        albedo: [f32; 4],
+        roughness: f32,
+        metallic: f32,
         refractive_index: f32,
         emission: Color,
     ) -> Self {
         Material {
             diffuse,
-            specular,
-            albedo,
+            roughness,
+            metallic,
+            transmission: 0.0,
             refractive_index,
             emission,
             has_texture: false,
             texture: None,
+            normal_map: None,
+            faces: None,
+            roughness_map: None,
+            metallic_map: None,
+            emission_map: None,
+            scroll: [0.0, 0.0],
+            absorption: Color::black(),
         }
     }
 
     pub fn new_with_texture(
-        specular: f32,
-        albedo: [f32; 4],
+        roughness: f32,
+        metallic: f32,
         refractive_index: f32,
-        texture: Arc<Texture>,
+        texture: TextureView,
         emission: Color,
     ) -> Self {
         Material {
-            diffuse: Color::new(255, 0, 0),
-            specular,
-            albedo,
+            diffuse: Color::new(1.0, 0.0, 0.0),
+            roughness,
+            metallic,
+            transmission: 0.0,
             refractive_index,
             emission,
             has_texture: true,
             texture: Some(texture),
+            normal_map: None,
+            faces: None,
+            roughness_map: None,
+            metallic_map: None,
+            emission_map: None,
+            scroll: [0.0, 0.0],
+            absorption: Color::black(),
+        }
+    }
+
+    pub fn with_normal_map(mut self, normal_map: TextureView) -> Self {
+        self.normal_map = Some(normal_map);
+        self
+    }
+
+    pub fn with_scroll(mut self, scroll: [f32; 2]) -> Self {
+        self.scroll = scroll;
+        self
+    }
+
+    pub fn with_faces(mut self, faces: [Option<FaceTexture>; 6]) -> Self {
+        self.faces = Some(Arc::new(faces));
+        self
+    }
+
+    pub fn with_transmission(mut self, transmission: f32) -> Self {
+        self.transmission = transmission;
+        self
+    }
+
+    pub fn with_roughness_map(mut self, roughness_map: TextureView) -> Self {
+        self.roughness_map = Some(roughness_map);
+        self
+    }
+
+    pub fn with_metallic_map(mut self, metallic_map: TextureView) -> Self {
+        self.metallic_map = Some(metallic_map);
+        self
+    }
+
+    pub fn with_emission_map(mut self, emission_map: TextureView) -> Self {
+        self.emission_map = Some(emission_map);
+        self
+    }
+
+    pub fn with_absorption(mut self, absorption: Color) -> Self {
+        self.absorption = absorption;
+        self
+    }
+
+    // Si la luz lo atraviesa (vidrio, agua, hielo)
+    pub fn is_transparent(&self) -> bool {
+        self.transmission > 0.0
+    }
+
+    // Fracción de la luz que sobrevive a recorrer `distance` dentro del material
+    pub fn transmittance(&self, distance: f32) -> Color {
+        Color::new(
+            (-self.absorption.r * distance).exp(),
+            (-self.absorption.g * distance).exp(),
+            (-self.absorption.b * distance).exp(),
+        )
+    }
+
+    // Si su aspecto cambia con el tiempo
+    pub fn is_animated(&self) -> bool {
+        let animated_texture = [
+            &self.texture,
+            &self.normal_map,
+            &self.roughness_map,
+            &self.metallic_map,
+            &self.emission_map,
+        ]
+        .into_iter()
+        .flatten()
+        .any(TextureView::is_animated);
+        animated_texture || self.scroll != [0.0, 0.0]
+    }
+
+    // Si se ve distinto en dos instantes del reloj de animación
+    pub fn changes_between(&self, from: f32, to: f32) -> bool {
+        let changed_texture = [
+            &self.texture,
+            &self.normal_map,
+            &self.roughness_map,
+            &self.metallic_map,
+            &self.emission_map,
+        ]
+        .into_iter()
+        .flatten()
+        .any(|texture| texture.changes_between(from, to));
+        changed_texture || (self.scroll != [0.0, 0.0] && from != to)
+    }
+
+    // Qué parte de la textura (en u y en v) cubre una cara de un bloque
+    pub fn face_span(&self, face: Face) -> (f32, f32) {
+        self.faces
+            .as_ref()
+            .and_then(|faces| faces[face.index()].as_ref())
+            .map_or((1.0, 1.0), FaceTexture::span)
+    }
+
+    // El material tal como se ve en una cara de un bloque: con la textura de esa cara y (u, v)
+    // y la base tangente llevadas a su rectángulo
+    pub fn on_face(
+        &self,
+        face: Face,
+        u: f32,
+        v: f32,
+        tangent: Vec3,
+        bitangent: Vec3,
+    ) -> (Material, (f32, f32), (Vec3, Vec3)) {
+        let Some(face_texture) = self
+            .faces
+            .as_ref()
+            .and_then(|faces| faces[face.index()].as_ref())
+        else {
+            return (self.clone(), (u, v), (tangent, bitangent));
+        };
+
+        let mut material = self.clone();
+        material.has_texture = true;
+        material.texture = Some(face_texture.texture.clone());
+        material.normal_map = face_texture.normal_map.clone();
+        let (uv, tangents) = face_texture.map(u, v, tangent, bitangent);
+        (material, uv, tangents)
+    }
+
+    // Con desplazamiento la textura se repite en lugar de quedarse en el borde
+    fn scrolled_uv(&self, u: f32, v: f32, lookup: &TextureLookup) -> (f32, f32) {
+        let time = lookup.time;
+        if self.scroll == [0.0, 0.0] {
+            return (u, v);
         }
+        (
+            (u + self.scroll[0] * time).rem_euclid(1.0),
+            (v + self.scroll[1] * time).rem_euclid(1.0),
+        )
     }
 
-    pub fn get_diffuse_color(&self, u: f32, v: f32) -> Color {
+    pub fn get_diffuse_color(&self, u: f32, v: f32, lookup: &TextureLookup) -> Color {
         if self.has_texture {
             if let Some(tex) = &self.texture {
-                let u = u.clamp(0.0, 1.0);
-                let v = v.clamp(0.0, 1.0);
-                let x = (u * (tex.width as f32)).round() as usize;
-                let y = ((1.0 - v) * (tex.height as f32)).round() as usize;
-                return tex.get_color(x.min(tex.width - 1), y.min(tex.height - 1));
+                let (u, v) = self.scrolled_uv(u, v, lookup);
+                return tex.sample(u, v, lookup);
             }
         }
         self.diffuse
     }
 
+    fn map_value(&self, map: &Option<TextureView>, u: f32, v: f32, lookup: &TextureLookup) -> f32 {
+        match map {
+            Some(map) => {
+                let (u, v) = self.scrolled_uv(u, v, lookup);
+                map.sample(u, v, lookup).r
+            }
+            None => 1.0,
+        }
+    }
+
+    // Color del mapa de emisión, o blanco si no tiene
+    pub fn emission_texture(&self, u: f32, v: f32, lookup: &TextureLookup) -> Color {
+        match &self.emission_map {
+            Some(map) => {
+                let (u, v) = self.scrolled_uv(u, v, lookup);
+                map.sample(u, v, lookup)
+            }
+            None => Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn get_emission(&self, u: f32, v: f32, lookup: &TextureLookup) -> Color {
+        self.emission * self.emission_texture(u, v, lookup)
+    }
+
+    // Emisión promedio sobre toda la superficie, con el mapa de emisión aplicado
+    pub fn average_emission(&self) -> Color {
+        match &self.emission_map {
+            Some(map) => self.emission * map.average(),
+            None => self.emission,
+        }
+    }
+
+    // Parámetros del modelo metálico-rugoso en un punto, con las texturas aplicadas
+    pub fn surface(&self, u: f32, v: f32, lookup: &TextureLookup) -> Surface {
+        Surface {
+            base_color: self.get_diffuse_color(u, v, lookup),
+            roughness: (self.roughness * self.map_value(&self.roughness_map, u, v, lookup))
+                .clamp(0.0, 1.0),
+            metallic: (self.metallic * self.map_value(&self.metallic_map, u, v, lookup))
+                .clamp(0.0, 1.0),
+            reflectance: dielectric_reflectance(self.refractive_index),
+        }
+    }
+
+    // Normal de sombreado: la normal geométrica inclinada según el mapa de normales. Sin mapa o
+    // sin base tangente (tangente nula) se devuelve la normal tal cual.
+    pub fn perturb_normal(
+        &self,
+        u: f32,
+        v: f32,
+        lookup: &TextureLookup,
+        normal: &Vec3,
+        tangent: &Vec3,
+        bitangent: &Vec3,
+    ) -> Vec3 {
+        let Some(normal_map) = &self.normal_map else {
+            return *normal;
+        };
+        if tangent.magnitude_squared() == 0.0 {
+            return *normal;
+        }
+
+        let (u, v) = self.scrolled_uv(u, v, lookup);
+        let sample = normal_map.sample(u, v, lookup);
+        let local = Vec3::new(
+            sample.r * 2.0 - 1.0,
+            sample.g * 2.0 - 1.0,
+            sample.b * 2.0 - 1.0,
+        );
+        let perturbed = tangent * local.x + bitangent * local.y + normal * local.z;
+        if perturbed.magnitude_squared() > 0.0 {
+            perturbed.normalize()
+        } else {
+            *normal
+        }
+    }
+
     pub fn black() -> Self {
         Material {
-            diffuse: Color::new(0, 0, 0),
-            specular: 0.0,
-            albedo: [0.0, 0.0, 0.0, 0.0],
-            refractive_index: 0.0,
-            emission: Color::new(0, 0, 0),
+            diffuse: Color::black(),
+            roughness: 1.0,
+            metallic: 0.0,
+            transmission: 0.0,
+            refractive_index: 1.5,
+            emission: Color::black(),
             has_texture: false,
             texture: None,
+            normal_map: None,
+            faces: None,
+            roughness_map: None,
+            metallic_map: None,
+            emission_map: None,
+            scroll: [0.0, 0.0],
+            absorption: Color::black(),
         }
     }
 }
@@ -0,0 +1,195 @@
+use crate::color::Color;
+use crate::sampling::Rng;
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+// Con α = 0 la distribución es una delta que no se puede evaluar; por debajo de esto el material
+// ya se ve como un espejo
+const MIN_ALPHA: f32 = 1e-3;
+
+// Un punto de una superficie en el modelo metálico-rugoso (el de glTF): un lóbulo difuso de
+// Lambert y uno especular de microfacetas GGX. Los metales no tienen difuso y tiñen el
+// especular con su color; los dieléctricos reflejan según su índice de refracción y lo que no
+// reflejan llega al difuso, así que la energía total nunca pasa de la que entra.
+#[derive(Debug, Clone, Copy)]
+pub struct Surface {
+    pub base_color: Color,
+    pub roughness: f32,
+    pub metallic: f32,
+    // Reflectancia a incidencia normal de la parte dieléctrica
+    pub reflectance: f32,
+}
+
+// Reflectancia a incidencia normal de un dieléctrico con índice `ior` en el aire
+pub fn dielectric_reflectance(ior: f32) -> f32 {
+    let r = (ior - 1.0) / (ior + 1.0);
+    r * r
+}
+
+pub fn fresnel_schlick(f0: Color, cos_theta: f32) -> Color {
+    let t = (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5);
+    Color::new(
+        f0.r + (1.0 - f0.r) * t,
+        f0.g + (1.0 - f0.g) * t,
+        f0.b + (1.0 - f0.b) * t,
+    )
+}
+
+fn one_minus(color: Color) -> Color {
+    Color::new(1.0 - color.r, 1.0 - color.g, 1.0 - color.b)
+}
+
+fn max_component(color: Color) -> f32 {
+    color.r.max(color.g).max(color.b)
+}
+
+// Distribución de normales de Trowbridge-Reitz (GGX)
+fn ggx_d(cos_h: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let d = cos_h * cos_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+fn smith_lambda(cos_theta: f32, alpha: f32) -> f32 {
+    let cos2 = cos_theta * cos_theta;
+    let tan2 = (1.0 - cos2).max(0.0) / cos2;
+    ((1.0 + alpha * alpha * tan2).sqrt() - 1.0) / 2.0
+}
+
+// Sombreado y enmascaramiento de Smith con correlación de altura
+fn smith_g(cos_o: f32, cos_i: f32, alpha: f32) -> f32 {
+    1.0 / (1.0 + smith_lambda(cos_o, alpha) + smith_lambda(cos_i, alpha))
+}
+
+// Dos ejes perpendiculares a `normal`
+pub(crate) fn tangent_frame(normal: &Vec3) -> (Vec3, Vec3) {
+    let helper = if normal.x.abs() > 0.9 {
+        Vec3::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let tangent = normal.cross(&helper).normalize();
+    let bitangent = normal.cross(&tangent);
+    (tangent, bitangent)
+}
+
+// Dirección con densidad cos θ / π alrededor de `normal`
+fn cosine_sample(normal: &Vec3, rng: &mut Rng) -> Vec3 {
+    let (u, v) = (rng.next_f32(), rng.next_f32());
+    let radius = u.sqrt();
+    let phi = 2.0 * PI * v;
+    let (tangent, bitangent) = tangent_frame(normal);
+
+    (tangent * (radius * phi.cos())
+        + bitangent * (radius * phi.sin())
+        + normal * (1.0 - u).max(0.0).sqrt())
+    .normalize()
+}
+
+// Normal de microfaceta con densidad D(h) cos θh
+fn ggx_sample(normal: &Vec3, alpha: f32, rng: &mut Rng) -> Vec3 {
+    let (u, v) = (rng.next_f32(), rng.next_f32());
+    let cos_theta = ((1.0 - u) / (1.0 + (alpha * alpha - 1.0) * u)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * v;
+    let (tangent, bitangent) = tangent_frame(normal);
+
+    (tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + normal * cos_theta)
+        .normalize()
+}
+
+// Dirección elegida por el material, con f · cos θ / pdf ya calculado
+pub struct BsdfSample {
+    pub direction: Vec3,
+    pub weight: Color,
+    pub pdf: f32,
+}
+
+impl Surface {
+    fn alpha(&self) -> f32 {
+        (self.roughness * self.roughness).max(MIN_ALPHA)
+    }
+
+    // Reflectancia a incidencia normal: la del dieléctrico o el color del metal
+    pub fn f0(&self) -> Color {
+        let dielectric = self.reflectance * (1.0 - self.metallic);
+        self.base_color * self.metallic + Color::new(dielectric, dielectric, dielectric)
+    }
+
+    fn diffuse_color(&self) -> Color {
+        self.base_color * (1.0 - self.metallic)
+    }
+
+    // Probabilidad de muestrear el lóbulo especular en lugar del difuso, según cuánta luz
+    // refleja cada uno visto desde `cos_o`
+    fn specular_probability(&self, cos_o: f32) -> f32 {
+        let fresnel = fresnel_schlick(self.f0(), cos_o);
+        let specular = max_component(fresnel);
+        let diffuse = max_component(self.diffuse_color() * one_minus(fresnel));
+        if specular + diffuse <= 0.0 {
+            return 1.0;
+        }
+        specular / (specular + diffuse)
+    }
+
+    // f · cos θ para la luz que llega desde `incoming` y sale hacia `outgoing`
+    pub fn eval(&self, normal: &Vec3, outgoing: &Vec3, incoming: &Vec3) -> Color {
+        let cos_o = normal.dot(outgoing);
+        let cos_i = normal.dot(incoming);
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return Color::black();
+        }
+
+        let half = (outgoing + incoming).normalize();
+        let cos_h = normal.dot(&half).max(0.0);
+        let fresnel = fresnel_schlick(self.f0(), outgoing.dot(&half));
+        let alpha = self.alpha();
+
+        let specular =
+            fresnel * (ggx_d(cos_h, alpha) * smith_g(cos_o, cos_i, alpha) / (4.0 * cos_o * cos_i));
+        let diffuse = self.diffuse_color() * one_minus(fresnel) * (1.0 / PI);
+        (specular + diffuse) * cos_i
+    }
+
+    // Densidad (por ángulo sólido) con la que `sample` elige `incoming`
+    pub fn pdf(&self, normal: &Vec3, outgoing: &Vec3, incoming: &Vec3) -> f32 {
+        let cos_o = normal.dot(outgoing);
+        let cos_i = normal.dot(incoming);
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return 0.0;
+        }
+
+        let half = (outgoing + incoming).normalize();
+        let cos_h = normal.dot(&half).max(0.0);
+        let specular_pdf =
+            ggx_d(cos_h, self.alpha()) * cos_h / (4.0 * outgoing.dot(&half).max(1e-6));
+        let diffuse_pdf = cos_i / PI;
+
+        let probability = self.specular_probability(cos_o);
+        probability * specular_pdf + (1.0 - probability) * diffuse_pdf
+    }
+
+    pub fn sample(&self, normal: &Vec3, outgoing: &Vec3, rng: &mut Rng) -> Option<BsdfSample> {
+        let cos_o = normal.dot(outgoing);
+        if cos_o <= 0.0 {
+            return None;
+        }
+
+        let incoming = if rng.next_f32() < self.specular_probability(cos_o) {
+            let half = ggx_sample(normal, self.alpha(), rng);
+            half * (2.0 * outgoing.dot(&half)) - outgoing
+        } else {
+            cosine_sample(normal, rng)
+        };
+
+        let pdf = self.pdf(normal, outgoing, &incoming);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            direction: incoming,
+            weight: self.eval(normal, outgoing, &incoming) * (1.0 / pdf),
+            pdf,
+        })
+    }
+}
@@ -0,0 +1,196 @@
+use crate::color::Color;
+use crate::differential::RayDifferential;
+use crate::microfacet::Surface;
+use crate::ray_intersect::{Intersect, RayIntersect};
+use crate::renderer::{fresnel, offset_point, reflect, refract, visibility, Shading};
+use crate::sampling::Rng;
+use crate::texture::TextureLookup;
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+// Tope de rebotes; la ruleta rusa corta casi todos los caminos mucho antes
+const MAX_BOUNCES: u32 = 64;
+// Rebotes garantizados antes de empezar con la ruleta rusa
+const MIN_BOUNCES: u32 = 3;
+
+// Heurística de la potencia (β = 2) de Veach para combinar dos estrategias de muestreo
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
+    }
+}
+
+fn max_component(color: Color) -> f32 {
+    color.r.max(color.g).max(color.b)
+}
+
+impl<T: RayIntersect> Shading<'_, T> {
+    // Un camino desde la cámara. En cada rebote, los materiales transparentes reflejan o
+    // refractan según el Fresnel exacto; el resto muestrea su BRDF metálica-rugosa. En los
+    // rebotes opacos se muestrea además cada luz (next event estimation) y las dos estimaciones
+    // de las luces de área se combinan con MIS. Los diferenciales siguen al camino para elegir
+    // el mipmap en cada rebote.
+    pub(crate) fn trace_path(
+        &self,
+        origin: &Vec3,
+        direction: &Vec3,
+        differential: &RayDifferential,
+        rng: &mut Rng,
+    ) -> Color {
+        let mut radiance = Color::black();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut origin = *origin;
+        let mut direction = direction.normalize();
+        let mut differential = *differential;
+        // Densidad con la que el material eligió `direction`; None desde la cámara o un espejo
+        let mut bsdf_pdf: Option<f32> = None;
+
+        for bounce in 0..MAX_BOUNCES {
+            let mut intersect = self.objects.intersect(&origin, &direction);
+            if !intersect.is_intersecting {
+                // Los discos del sol y la luna solo se ven directamente: como luz ya se muestrean
+                let sky = match bsdf_pdf {
+                    None => self.environment.background(&direction),
+                    Some(_) => self.environment.sky(&direction),
+                };
+                radiance = radiance + throughput * sky;
+                break;
+            }
+
+            let geometric_normal = intersect.normal;
+            // Un rayo que cruzó un material transparente perdió parte de la luz en el camino
+            throughput = throughput * intersect.transmittance(&direction);
+            let hit = differential.at_surface(&direction, &intersect);
+            let lookup = self.lookup(&intersect, &hit);
+            intersect.normal = intersect.shading_normal(&lookup);
+
+            if !intersect.material.emission.is_black() {
+                let emitted =
+                    self.emitted(&intersect, &geometric_normal, &origin, bsdf_pdf, &lookup);
+                radiance = radiance + throughput * emitted;
+            }
+
+            let material = &intersect.material;
+            let surface = material.surface(intersect.u, intersect.v, &lookup);
+            let outgoing = -direction;
+
+            if rng.next_f32() < material.transmission {
+                // Dieléctrico liso: se refleja con probabilidad igual al Fresnel y si no se
+                // refracta, así que el peso de cada rama es 1 (más el tinte del color base)
+                let fresnel = fresnel(&direction, &intersect.normal, material.refractive_index);
+                let refracted = refract(&direction, &intersect.normal, material.refractive_index);
+                match refracted {
+                    Some(refracted) if rng.next_f32() >= fresnel => {
+                        differential = hit.refract(
+                            &direction,
+                            &refracted,
+                            &intersect.normal,
+                            material.refractive_index,
+                        );
+                        direction = refracted;
+                        throughput = throughput * surface.base_color;
+                    }
+                    _ => {
+                        differential = hit.reflect(&intersect.normal);
+                        direction = reflect(&direction, &intersect.normal).normalize();
+                    }
+                }
+                bsdf_pdf = None;
+            } else {
+                let opacity = 1.0 - material.transmission;
+                let direct = self.direct_light(&intersect, &surface, &outgoing, opacity, rng);
+                radiance = radiance + throughput * direct * (1.0 / opacity);
+
+                let Some(sample) = surface.sample(&intersect.normal, &outgoing, rng) else {
+                    break;
+                };
+                direction = sample.direction;
+                // El lóbulo abre el rayo tanto como su α: un espejo casi nada, un difuso mucho
+                differential = hit.scatter(&direction, surface.roughness * surface.roughness);
+                bsdf_pdf = Some(sample.pdf * opacity);
+                throughput = throughput * sample.weight * (1.0 / opacity);
+            }
+            origin = offset_point(&intersect, &direction);
+
+            // Ruleta rusa: los caminos que ya aportan poco se cortan al azar, y los que siguen
+            // se refuerzan para no perder energía en promedio
+            if bounce >= MIN_BOUNCES {
+                let survival = max_component(throughput).clamp(0.05, 0.95);
+                if rng.next_f32() > survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+        }
+
+        radiance
+    }
+
+    // Luz que sale de una superficie emisiva. Si pertenece a una luz de área, se escala como la
+    // luz (de noche). Si se llegó por un rebote que no fue especular perfecto, se pondera con
+    // MIS contra el muestreo de esa luz, y para eso las dos estrategias tienen que estimar la
+    // misma radiancia: la media de la luz, sin el detalle del mapa de emisión.
+    fn emitted(
+        &self,
+        intersect: &Intersect,
+        geometric_normal: &Vec3,
+        previous_point: &Vec3,
+        bsdf_pdf: Option<f32>,
+        lookup: &TextureLookup,
+    ) -> Color {
+        let material = &intersect.material;
+        let texture = material.emission_texture(intersect.u, intersect.v, lookup);
+        let Some(light) = self
+            .lights
+            .iter()
+            .find(|light| light.emits_from(&intersect.point))
+        else {
+            return material.emission * texture * self.environment.emission;
+        };
+
+        match bsdf_pdf {
+            None => material.emission * texture * light.intensity(),
+            Some(pdf) => {
+                let light_pdf = light.pdf(previous_point, &intersect.point, geometric_normal);
+                light.color() * light.intensity() * power_heuristic(pdf, light_pdf)
+            }
+        }
+    }
+
+    // Next event estimation: una muestra de cada luz, con su sombra. `opacity` es la
+    // probabilidad de haber llegado al lóbulo opaco, para la ponderación MIS.
+    fn direct_light(
+        &self,
+        intersect: &Intersect,
+        surface: &Surface,
+        outgoing: &Vec3,
+        opacity: f32,
+        rng: &mut Rng,
+    ) -> Color {
+        let mut total = Color::black();
+        for light in self.lights {
+            let Some(sample) = light.sample(&intersect.point, rng.next_f32(), rng.next_f32())
+            else {
+                continue;
+            };
+            let reflected = surface.eval(&intersect.normal, outgoing, &sample.direction);
+            if reflected.is_black() || visibility(intersect, &sample, self.objects) <= 0.0 {
+                continue;
+            }
+
+            let weight = match sample.pdf {
+                Some(light_pdf) => {
+                    let bsdf_pdf = surface.pdf(&intersect.normal, outgoing, &sample.direction);
+                    power_heuristic(light_pdf, bsdf_pdf * opacity)
+                }
+                None => 1.0,
+            };
+            // `sample.color` es lo que refleja un difuso blanco (E / π), de ahí el factor π
+            total = total + reflected * sample.color * (PI * weight);
+        }
+        total
+    }
+}
@@ -1,5 +1,9 @@
+use crate::aabb::Aabb;
+use crate::color::Color;
 use crate::material::Material;
+use crate::texture::TextureLookup;
 use nalgebra_glm::Vec3;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct Intersect {
@@ -10,6 +14,15 @@ pub struct Intersect {
     pub material: Material,
     pub u: f32,
     pub v: f32,
+    // Direcciones en las que crecen u y v sobre la superficie, para los mapas de normales
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    // Distancia que recorre en el mundo una unidad de u y una de v, para saber cuánto de la
+    // textura cubre un píxel; 0 si no se conoce
+    pub uv_scale: (f32, f32),
+    // Luz que deja pasar el medio transparente que el rayo cruzó hasta el impacto, cuando el
+    // objeto lo sabe (el agua sobre el fondo de un estanque); si no, se deduce de la normal
+    pub medium: Option<Color>,
 }
 
 impl Intersect {
@@ -29,6 +42,10 @@ impl Intersect {
             material,
             u,
             v,
+            tangent: Vec3::new(0.0, 0.0, 0.0),
+            bitangent: Vec3::new(0.0, 0.0, 0.0),
+            uv_scale: (0.0, 0.0),
+            medium: None,
         }
     }
 
@@ -41,10 +58,68 @@ impl Intersect {
             material: Material::black(),
             u: 0.0,
             v: 0.0,
+            tangent: Vec3::new(0.0, 0.0, 0.0),
+            bitangent: Vec3::new(0.0, 0.0, 0.0),
+            uv_scale: (0.0, 0.0),
+            medium: None,
         }
     }
+
+    pub fn with_tangents(mut self, tangent: Vec3, bitangent: Vec3) -> Self {
+        self.tangent = tangent;
+        self.bitangent = bitangent;
+        self
+    }
+
+    pub fn with_uv_scale(mut self, scale_u: f32, scale_v: f32) -> Self {
+        self.uv_scale = (scale_u, scale_v);
+        self
+    }
+
+    pub fn with_medium(mut self, transmittance: Color) -> Self {
+        self.medium = Some(transmittance);
+        self
+    }
+
+    // Fracción de la luz que sobrevive al tramo entre el origen del rayo y el impacto. Sin medio
+    // conocido, un rayo que sale de un material transparente lo recorrió por dentro.
+    pub fn transmittance(&self, ray_direction: &Vec3) -> Color {
+        match self.medium {
+            Some(transmittance) => transmittance,
+            None if ray_direction.dot(&self.normal) > 0.0 => {
+                self.material.transmittance(self.distance)
+            }
+            None => Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    // Normal con la que se sombrea el punto, después de aplicar el mapa de normales
+    pub fn shading_normal(&self, lookup: &TextureLookup) -> Vec3 {
+        self.material.perturb_normal(
+            self.u,
+            self.v,
+            lookup,
+            &self.normal,
+            &self.tangent,
+            &self.bitangent,
+        )
+    }
 }
 
 pub trait RayIntersect {
     fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect;
+    fn aabb(&self) -> Aabb;
+}
+
+// Cualquier primitiva de la escena: cubos, esferas, mundos de bloques...
+pub type Object = Arc<dyn RayIntersect + Send + Sync>;
+
+impl<T: RayIntersect + ?Sized> RayIntersect for Arc<T> {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        (**self).ray_intersect(ray_origin, ray_direction)
+    }
+
+    fn aabb(&self) -> Aabb {
+        (**self).aabb()
+    }
 }
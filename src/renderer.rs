@@ -0,0 +1,454 @@
+use crate::bvh::Bvh;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::cycle;
+use crate::differential::{RayDifferential, SurfaceDifferential};
+use crate::environment::Environment;
+use crate::filter::Filter;
+use crate::framebuffer::Framebuffer;
+use crate::light::{Light, LightSample};
+use crate::microfacet::{fresnel_schlick, Surface};
+use crate::ray_intersect::{Intersect, RayIntersect};
+use crate::sampling::{self, Rng, SamplePattern};
+use crate::texture::{TextureFilter, TextureLookup};
+use clap::ValueEnum;
+use nalgebra_glm::Vec3;
+use rayon::prelude::*;
+use std::f32::consts::PI;
+use std::fmt;
+
+const BIAS: f32 = 0.001;
+// Rebotes de reflexión y refracción; atravesar un bloque de vidrio ya usa dos
+const MAX_DEPTH: u32 = 5;
+const LIGHT_EPSILON: f32 = 0.01;
+// Por debajo de esto no vale la pena lanzar el rayo reflejado
+const MIN_REFLECTANCE: f32 = 0.01;
+// Filas que traza cada tarea; cada franja guarda solo sus sumas y las de los vecinos del filtro
+const BAND_HEIGHT: usize = 8;
+
+// Aleja el origen de un rayo secundario de la superficie, del lado hacia el que sale
+pub(crate) fn offset_point(intersect: &Intersect, direction: &Vec3) -> Vec3 {
+    let offset = intersect.normal * BIAS;
+    if direction.dot(&intersect.normal) >= 0.0 {
+        intersect.point + offset
+    } else {
+        intersect.point - offset
+    }
+}
+
+pub(crate) fn reflect(incident: &Vec3, normal: &Vec3) -> Vec3 {
+    incident - 2.0 * incident.dot(normal) * normal
+}
+
+// Dirección al pasar a un medio de índice `ior`, o al salir de él si el rayo viene desde
+// adentro (del lado opuesto a `normal`). None si hay reflexión total interna.
+pub(crate) fn refract(incident: &Vec3, normal: &Vec3, ior: f32) -> Option<Vec3> {
+    let cos_i = -incident.dot(normal).clamp(-1.0, 1.0);
+    let (eta, cos_i, normal) = if cos_i < 0.0 {
+        (ior, -cos_i, -normal)
+    } else {
+        (1.0 / ior, cos_i, *normal)
+    };
+
+    let k = 1.0 - eta * eta * (1.0 - cos_i * cos_i);
+    if k < 0.0 {
+        None
+    } else {
+        Some((incident * eta + normal * (eta * cos_i - k.sqrt())).normalize())
+    }
+}
+
+// Reflectancia de Fresnel de un dieléctrico para luz sin polarizar (promedio de las
+// componentes s y p). Un índice de 0 o menos se toma como espejo perfecto.
+pub(crate) fn fresnel(incident: &Vec3, normal: &Vec3, ior: f32) -> f32 {
+    if ior <= 0.0 {
+        return 1.0;
+    }
+    let cos_i = -incident.dot(normal).clamp(-1.0, 1.0);
+    let (eta_i, eta_t, cos_i) = if cos_i < 0.0 {
+        (ior, 1.0, -cos_i)
+    } else {
+        (1.0, ior, cos_i)
+    };
+
+    let sin_t = eta_i / eta_t * (1.0 - cos_i * cos_i).max(0.0).sqrt();
+    if sin_t >= 1.0 {
+        // Reflexión total interna
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin_t * sin_t).max(0.0).sqrt();
+
+    let r_s = (eta_i * cos_i - eta_t * cos_t) / (eta_i * cos_i + eta_t * cos_t);
+    let r_p = (eta_t * cos_i - eta_i * cos_t) / (eta_t * cos_i + eta_i * cos_t);
+    (r_s * r_s + r_p * r_p) / 2.0
+}
+
+// 1 si nada tapa el camino entre el punto y la muestra de la luz, 0 si algo lo tapa
+pub(crate) fn visibility<T: RayIntersect>(
+    intersect: &Intersect,
+    sample: &LightSample,
+    objects: &Bvh<T>,
+) -> f32 {
+    let light_dir = sample.direction;
+    // Las muestras de luces de área están sobre la superficie del emisor; se corta un poco antes
+    // para no contar al propio emisor como obstáculo
+    let light_distance = sample.distance - LIGHT_EPSILON;
+    let shadow_ray_origin = offset_point(intersect, &light_dir);
+
+    match objects.any_hit(&shadow_ray_origin, &light_dir, light_distance) {
+        Some(_) => 0.0,
+        None => 1.0,
+    }
+}
+
+pub fn cast_ray<T: RayIntersect>(
+    ray_origin: &Vec3,
+    ray_direction: &Vec3,
+    objects: &Bvh<T>,
+    lights: &[Light],
+    depth: u32,
+    environment: &Environment,
+    time: f32,
+) -> Color {
+    let shading = Shading {
+        objects,
+        lights,
+        environment,
+        time,
+        // Sin diferenciales no hay huella; las texturas se leen sin filtrar
+        texture_filter: TextureFilter::Nearest,
+    };
+    let seed = ray_direction.x.to_bits() as u64 ^ ((ray_direction.y.to_bits() as u64) << 32);
+    shading.cast_ray(
+        ray_origin,
+        ray_direction,
+        &RayDifferential::default(),
+        depth,
+        &mut Rng::new(seed),
+    )
+}
+
+// Lo que comparten todos los rayos de un cuadro; `time` (en segundos) anima las texturas
+pub(crate) struct Shading<'a, T: RayIntersect> {
+    pub(crate) objects: &'a Bvh<T>,
+    pub(crate) lights: &'a [Light],
+    pub(crate) environment: &'a Environment,
+    pub(crate) time: f32,
+    pub(crate) texture_filter: TextureFilter,
+}
+
+impl<T: RayIntersect> Shading<'_, T> {
+    // Cómo se leen las texturas en un impacto, según la huella del píxel en la superficie
+    pub(crate) fn lookup(&self, intersect: &Intersect, hit: &SurfaceDifferential) -> TextureLookup {
+        TextureLookup {
+            time: self.time,
+            filter: self.texture_filter,
+            footprint: hit.footprint(intersect),
+        }
+    }
+
+    fn cast_ray(
+        &self,
+        ray_origin: &Vec3,
+        ray_direction: &Vec3,
+        differential: &RayDifferential,
+        depth: u32,
+        rng: &mut Rng,
+    ) -> Color {
+        if depth >= MAX_DEPTH {
+            return self.environment.background(ray_direction);
+        }
+
+        let mut intersect = self.objects.intersect(ray_origin, ray_direction);
+
+        if !intersect.is_intersecting {
+            return self.environment.background(ray_direction);
+        }
+
+        // Si el rayo cruzó un material transparente, parte de la luz se absorbió en el camino
+        let absorption = intersect.transmittance(ray_direction);
+
+        let hit = differential.at_surface(ray_direction, &intersect);
+        let lookup = self.lookup(&intersect, &hit);
+
+        // Difuso, especular, reflexión y refracción usan la normal del mapa de normales
+        intersect.normal = intersect.shading_normal(&lookup);
+        let surface = intersect
+            .material
+            .surface(intersect.u, intersect.v, &lookup);
+        let emission = intersect
+            .material
+            .get_emission(intersect.u, intersect.v, &lookup);
+
+        let mut color = self.environment.ambient;
+
+        if !emission.is_black() {
+            color = color + emission * self.environment.emission;
+        }
+
+        for light in self.lights {
+            let light_effect =
+                self.calculate_lighting(&intersect, &surface, light, ray_direction, rng);
+
+            color = color + light_effect;
+        }
+
+        color =
+            color + self.reflect_and_refract(&intersect, &surface, ray_direction, &hit, depth, rng);
+
+        color * absorption
+    }
+
+    fn calculate_lighting(
+        &self,
+        intersect: &Intersect,
+        surface: &Surface,
+        light: &Light,
+        ray_direction: &Vec3,
+        rng: &mut Rng,
+    ) -> Color {
+        let mut total_light = Color::black();
+
+        let view_dir = (-ray_direction).normalize();
+        // La parte que deja pasar la luz no la refleja
+        let opacity = 1.0 - intersect.material.transmission;
+
+        // Las luces de área se muestrean en una cuadrícula estratificada sobre su superficie; la
+        // fracción de muestras visibles da la penumbra. Un foco fuera de su cono o un panel visto
+        // por detrás no iluminan, y la reflexión y la refracción se suman aparte.
+        let grid = light.sample_grid();
+        let weight = 1.0 / (grid * grid) as f32;
+        let points: Vec<(f32, f32)> = if grid == 1 {
+            vec![(0.5, 0.5)]
+        } else {
+            sampling::stratified(grid, rng).collect()
+        };
+        for (u, v) in points {
+            let Some(sample) = light.sample(&intersect.point, u, v) else {
+                continue;
+            };
+            if intersect.normal.dot(&sample.direction) <= 0.0 {
+                continue;
+            }
+            let light_color =
+                sample.color * (visibility(intersect, &sample, self.objects) * weight);
+
+            // `light_color` es lo que refleja un difuso blanco (E / π), de ahí el factor π
+            let reflected = surface.eval(&intersect.normal, &view_dir, &sample.direction);
+            total_light = total_light + reflected * light_color * (PI * opacity);
+        }
+
+        total_light
+    }
+
+    // Reflexión y refracción. Whitted solo sigue el reflejo especular perfecto, así que el de
+    // los materiales opacos se pondera con el Fresnel del lóbulo GGX y se apaga a medida que
+    // son más rugosos. Los transparentes reflejan según el Fresnel exacto y refractan el
+    // resto, teñido por su color; con reflexión total interna todo se refleja. Los dos rayos
+    // llevan sus diferenciales, así que lo que se ve en un reflejo también se filtra.
+    fn reflect_and_refract(
+        &self,
+        intersect: &Intersect,
+        surface: &Surface,
+        ray_direction: &Vec3,
+        hit: &SurfaceDifferential,
+        depth: u32,
+        rng: &mut Rng,
+    ) -> Color {
+        let material = &intersect.material;
+        let transmission = material.transmission;
+        let fresnel = if material.is_transparent() {
+            fresnel(ray_direction, &intersect.normal, material.refractive_index)
+        } else {
+            0.0
+        };
+
+        let cos_o = -ray_direction.dot(&intersect.normal);
+        let glossiness = (1.0 - surface.roughness).powi(2) * (1.0 - transmission);
+        let reflectance = fresnel_schlick(surface.f0(), cos_o) * glossiness
+            + Color::new(1.0, 1.0, 1.0) * (fresnel * transmission);
+
+        let mut color = Color::black();
+        if reflectance.r.max(reflectance.g).max(reflectance.b) > MIN_REFLECTANCE {
+            let reflect_dir = reflect(ray_direction, &intersect.normal).normalize();
+            let reflect_origin = offset_point(intersect, &reflect_dir);
+            let differential = hit.reflect(&intersect.normal);
+            color = self.cast_ray(&reflect_origin, &reflect_dir, &differential, depth + 1, rng)
+                * reflectance;
+        }
+
+        if material.is_transparent() {
+            if let Some(refract_dir) =
+                refract(ray_direction, &intersect.normal, material.refractive_index)
+            {
+                let refract_origin = offset_point(intersect, &refract_dir);
+                let differential = hit.refract(
+                    ray_direction,
+                    &refract_dir,
+                    &intersect.normal,
+                    material.refractive_index,
+                );
+                color = color
+                    + self.cast_ray(&refract_origin, &refract_dir, &differential, depth + 1, rng)
+                        * surface.base_color
+                        * ((1.0 - fresnel) * transmission);
+            }
+        }
+
+        color
+    }
+}
+
+// Cómo se calcula la luz de cada rayo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Integrator {
+    // Whitted: luz directa, reflexión y refracción, y un término ambiental constante
+    Whitted,
+    // Path tracing de Monte Carlo con iluminación global
+    #[value(name = "path")]
+    PathTracer,
+}
+
+impl fmt::Display for Integrator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        cycle::write_name(self, f)
+    }
+}
+
+// Ajustes de un cuadro que no son parte de la escena
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    // Rayos por píxel en cada cuadro
+    pub samples: u32,
+    // Instante de las texturas animadas, en segundos
+    pub time: f32,
+    pub integrator: Integrator,
+    pub pattern: SamplePattern,
+    pub filter: Filter,
+    pub texture_filter: TextureFilter,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            samples: 1,
+            time: 0.0,
+            integrator: Integrator::Whitted,
+            pattern: SamplePattern::Stratified,
+            filter: Filter::Box,
+            texture_filter: TextureFilter::Trilinear,
+        }
+    }
+}
+
+pub fn render<T: RayIntersect + Sync>(
+    framebuffer: &mut Framebuffer,
+    objects: &Bvh<T>,
+    camera: &Camera,
+    lights: &[Light],
+    environment: &Environment,
+    options: &RenderOptions,
+) {
+    let width = framebuffer.width as f32;
+    let height = framebuffer.height as f32;
+    let aspect_ratio = width / height;
+    let fov = PI / 3.0;
+    let perspective_scale = (fov / 2.0).tan();
+    let samples = options.samples.max(1);
+    // Cada cuadro usa otra semilla para que sus muestras se sumen a las anteriores
+    let first_sample = framebuffer.accumulated_samples;
+    let (frame_width, frame_height) = (framebuffer.width, framebuffer.height);
+
+    let shading = Shading {
+        objects,
+        lights,
+        environment,
+        time: options.time,
+        texture_filter: options.texture_filter,
+    };
+
+    // Dirección del rayo que pasa por un punto de la imagen (en píxeles)
+    let direction_at = |image_x: f32, image_y: f32| {
+        let screen_x = (2.0 * image_x) / width - 1.0;
+        let screen_y = -(2.0 * image_y) / height + 1.0;
+        let screen_x = screen_x * aspect_ratio * perspective_scale;
+        let screen_y = screen_y * perspective_scale;
+        camera.basis_change(&Vec3::new(screen_x, screen_y, -1.0).normalize())
+    };
+    // Con varias muestras por píxel cada una cubre una parte del píxel, así que su huella se
+    // achica (como en pbrt)
+    let footprint_scale = (1.0 / (samples as f32).sqrt()).max(0.125);
+
+    // Cada muestra se suma, con el peso del filtro a esa distancia de su centro, a los píxeles
+    // vecinos que caen dentro del filtro. Cada franja de filas acumula en su propio buffer, que
+    // se extiende `reach` filas arriba y abajo; así la memoria no crece con las muestras.
+    let reach = (options.filter.radius() - 0.5).ceil().max(0.0) as usize;
+    let bands: Vec<(usize, Vec<(Color, f32)>)> = (0..frame_height)
+        .into_par_iter()
+        .step_by(BAND_HEIGHT)
+        .map(|top| {
+            let bottom = (top + BAND_HEIGHT).min(frame_height);
+            let first_row = top.saturating_sub(reach);
+            let last_row = (bottom + reach).min(frame_height);
+            let mut sums = vec![(Color::black(), 0.0); (last_row - first_row) * frame_width];
+            for y in top..bottom {
+                for x in 0..frame_width {
+                    let pixel = (y * frame_width + x) as u64;
+                    let mut rng = Rng::new(pixel | (first_sample as u64) << 32);
+                    for sample in 0..samples {
+                        let (u, v) = options.pattern.sample(sample, samples, &mut rng);
+                        let (image_x, image_y) = (x as f32 + u, y as f32 + v);
+                        let direction = direction_at(image_x, image_y);
+                        let differential = RayDifferential::from_camera(
+                            &direction,
+                            &direction_at(image_x + 1.0, image_y),
+                            &direction_at(image_x, image_y + 1.0),
+                        )
+                        .scale(footprint_scale);
+                        let color = match options.integrator {
+                            Integrator::Whitted => shading.cast_ray(
+                                &camera.eye,
+                                &direction,
+                                &differential,
+                                0,
+                                &mut rng,
+                            ),
+                            Integrator::PathTracer => {
+                                shading.trace_path(&camera.eye, &direction, &differential, &mut rng)
+                            }
+                        };
+
+                        for ny in y.saturating_sub(reach)..(y + reach + 1).min(frame_height) {
+                            for nx in x.saturating_sub(reach)..(x + reach + 1).min(frame_width) {
+                                let weight = options.filter.weight(
+                                    image_x - (nx as f32 + 0.5),
+                                    image_y - (ny as f32 + 0.5),
+                                );
+                                if weight != 0.0 {
+                                    let (sum, weight_sum) =
+                                        &mut sums[(ny - first_row) * frame_width + nx];
+                                    *sum = *sum + color * weight;
+                                    *weight_sum += weight;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            (first_row, sums)
+        })
+        .collect();
+
+    // Las franjas se solapan en los bordes del filtro; se suman en el buffer del cuadro
+    let mut sums = vec![(Color::black(), 0.0); frame_width * frame_height];
+    for (first_row, band) in bands {
+        let offset = first_row * frame_width;
+        for (total, (sum, weight)) in sums[offset..offset + band.len()].iter_mut().zip(band) {
+            total.0 = total.0 + sum;
+            total.1 += weight;
+        }
+    }
+
+    framebuffer.accumulate(&sums, samples);
+    framebuffer.resolve();
+}
@@ -0,0 +1,83 @@
+use crate::cycle;
+use clap::ValueEnum;
+use std::fmt;
+
+// Generador pseudoaleatorio PCG32. Cada píxel usa su propia secuencia, así el render es
+// reproducible aunque los píxeles se repartan entre hilos.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+const MULTIPLIER: u64 = 6364136223846793005;
+const INCREMENT: u64 = 1442695040888963407;
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    // Uniforme en [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+// Puntos estratificados en el cuadrado unitario: una cuadrícula de `n` × `n` celdas con un
+// punto al azar dentro de cada una
+pub fn stratified(n: u32, rng: &mut Rng) -> impl Iterator<Item = (f32, f32)> + '_ {
+    let cell = 1.0 / n as f32;
+    (0..n * n).map(move |i| {
+        let (column, row) = ((i % n) as f32, (i / n) as f32);
+        (
+            (column + rng.next_f32()) * cell,
+            (row + rng.next_f32()) * cell,
+        )
+    })
+}
+
+// Cómo se reparten las muestras de un píxel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SamplePattern {
+    // Cada muestra en cualquier lugar del píxel
+    Jittered,
+    // Una muestra por celda de una cuadrícula, en un lugar al azar de la celda
+    Stratified,
+}
+
+impl SamplePattern {
+    // Muestra `index` de `count` en el cuadrado unitario. Si `count` no es un cuadrado, las
+    // muestras se reparten entre las celdas de la cuadrícula más chica que las contiene.
+    pub fn sample(self, index: u32, count: u32, rng: &mut Rng) -> (f32, f32) {
+        match self {
+            SamplePattern::Jittered => (rng.next_f32(), rng.next_f32()),
+            SamplePattern::Stratified => {
+                let n = (count as f32).sqrt().ceil().max(1.0) as u32;
+                let cell = (index as u64 * (n * n) as u64 / count.max(1) as u64) as u32;
+                let (column, row) = ((cell % n) as f32, (cell / n) as f32);
+                (
+                    (column + rng.next_f32()) / n as f32,
+                    (row + rng.next_f32()) / n as f32,
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for SamplePattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        cycle::write_name(self, f)
+    }
+}
@@ -0,0 +1,1175 @@
+use crate::atlas::TextureRegistry;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::cube::{Cube, Face};
+use crate::day_cycle::DayCycle;
+use crate::environment::Environment;
+use crate::light::{AreaShape, Light};
+use crate::material::{FaceTexture, Material};
+use crate::ray_intersect::Object;
+use crate::sky::Sky;
+use crate::sphere::Sphere;
+use crate::texture::{Animation, Texture, TextureView};
+use crate::voxel::BlockId;
+use crate::world::World;
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use toml::Spanned;
+
+pub struct Scene {
+    pub world: Arc<World>,
+    // Primitivas fuera de la cuadrícula de bloques (esferas, cubos de tamaño libre)
+    pub shapes: Vec<Object>,
+    pub lights: Vec<Light>,
+    // Luces que se encienden al atardecer junto con los bloques emisivos
+    pub lamps: Vec<Light>,
+    pub camera: Camera,
+    pub skybox: Arc<Texture>,
+    pub night_skybox: Option<Arc<Texture>>,
+    pub day_cycle: Option<DayCycle>,
+    pub sky: Option<SkySettings>,
+    // Archivo de escena y todas las imágenes que carga, para recargar en caliente
+    pub files: Vec<PathBuf>,
+    // Los materiales con textura animada o desplazamiento
+    animated_materials: Vec<Material>,
+}
+
+// Cielo analítico. Con ciclo de día el sol lo mueve el ciclo; si no, queda fijo en
+// `sun_direction` y se agrega como luz.
+#[derive(Debug, Clone)]
+pub struct SkySettings {
+    pub turbidity: f32,
+    pub sun_direction: Vec3,
+    pub sun_intensity: f32,
+}
+
+impl Scene {
+    // El mundo de bloques junto con las demás primitivas, listo para el BVH
+    pub fn objects(&self) -> Vec<Object> {
+        let mut objects: Vec<Object> = vec![self.world.clone()];
+        objects.extend(self.shapes.iter().cloned());
+        objects
+    }
+
+    // Si algún material se ve distinto entre dos instantes del reloj de animación; el ciclo
+    // de día se revisa aparte, con su hora
+    pub fn changes_between(&self, from: f32, to: f32) -> bool {
+        self.animated_materials
+            .iter()
+            .any(|material| material.changes_between(from, to))
+    }
+
+    // Dirección hacia el sol, la misma para la luz y para el cielo
+    pub fn sun_direction(&self) -> Option<Vec3> {
+        match (&self.day_cycle, &self.sky) {
+            (Some(cycle), _) => Some(cycle.sun_direction()),
+            (None, Some(sky)) => Some(sky.sun_direction),
+            (None, None) => None,
+        }
+    }
+
+    // Color del sol al atravesar la atmósfera; la luz y el disco del cielo usan el mismo
+    pub fn sun_color(&self) -> Option<Color> {
+        match (&self.day_cycle, &self.sky, self.sun_direction()) {
+            (_, Some(sky), Some(sun_direction)) => {
+                Some(Sky::new(sun_direction, sky.turbidity).sun_color())
+            }
+            (Some(cycle), None, _) => Some(cycle.sun_color()),
+            _ => None,
+        }
+    }
+
+    // Luces del instante actual: las fijas, el sol y la luna del ciclo de día y las lámparas
+    pub fn lights(&self) -> Vec<Light> {
+        let mut lights = self.lights.clone();
+        let sun_color = self.sun_color().unwrap_or_else(Color::black);
+        if let (None, Some(sky)) = (&self.day_cycle, &self.sky) {
+            lights.push(Light::Directional {
+                direction: -sky.sun_direction,
+                color: sun_color,
+                intensity: sky.sun_intensity,
+            });
+        }
+        match &self.day_cycle {
+            Some(cycle) => {
+                lights.extend(cycle.lights(sun_color));
+
+                let strength = cycle.lamp_strength();
+                if strength > 0.0 {
+                    lights.extend(self.lamps.iter().map(|lamp| lamp.scaled(strength)));
+                }
+            }
+            None => lights.extend(self.lamps.iter().cloned()),
+        }
+        lights
+    }
+
+    pub fn environment(&self) -> Environment {
+        let mut environment = Environment::new(self.skybox.clone());
+        environment.night_skybox = self.night_skybox.clone();
+        if let Some(cycle) = &self.day_cycle {
+            environment.daylight = cycle.daylight();
+            environment.ambient = cycle.ambient();
+            environment.emission = cycle.lamp_strength();
+        }
+        if let (Some(sky), Some(sun_direction)) = (&self.sky, self.sun_direction()) {
+            environment.sky = Some(Sky::new(sun_direction, sky.turbidity));
+        }
+        environment.sun = self.sun_direction().zip(self.sun_color());
+        environment
+    }
+}
+
+#[derive(Debug)]
+pub struct SceneError {
+    pub path: PathBuf,
+    // (línea, columna), empezando en 1
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location {
+            Some((line, column)) => write!(
+                f,
+                "{}:{}:{}: {}",
+                self.path.display(),
+                line,
+                column,
+                self.message
+            ),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    skybox: Spanned<String>,
+    night_skybox: Option<Spanned<String>>,
+    // Carpeta de tiles que se empaquetan en un atlas; sus nombres sirven como texturas
+    tiles: Option<Spanned<String>>,
+    camera: CameraDesc,
+    day_cycle: Option<DayCycleDesc>,
+    sky: Option<SkyDesc>,
+    #[serde(default)]
+    textures: HashMap<String, Spanned<String>>,
+    #[serde(default)]
+    normal_maps: HashMap<String, Spanned<String>>,
+    #[serde(default)]
+    maps: HashMap<String, Spanned<String>>,
+    #[serde(default)]
+    animations: HashMap<String, Spanned<AnimationDesc>>,
+    #[serde(default)]
+    materials: BTreeMap<String, Spanned<MaterialDesc>>,
+    #[serde(default)]
+    lights: Vec<Spanned<LightDesc>>,
+    #[serde(default)]
+    blocks: Vec<Spanned<BlockDesc>>,
+    #[serde(default)]
+    cubes: Vec<CubeDesc>,
+    #[serde(default)]
+    spheres: Vec<SphereDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    eye: [f32; 3],
+    center: [f32; 3],
+    #[serde(default = "default_up")]
+    up: [f32; 3],
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DayCycleDesc {
+    #[serde(default = "default_hour")]
+    hour: f32,
+    #[serde(default = "default_day_speed")]
+    speed: f32,
+    #[serde(default = "default_sun_intensity")]
+    sun_intensity: f32,
+    #[serde(default = "default_moon_intensity")]
+    moon_intensity: f32,
+}
+
+fn default_hour() -> f32 {
+    12.0
+}
+
+fn default_day_speed() -> f32 {
+    0.5
+}
+
+fn default_sun_intensity() -> f32 {
+    1.0
+}
+
+fn default_moon_intensity() -> f32 {
+    0.15
+}
+
+// Ángulos en grados; el azimut se mide desde +z hacia +x
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SkyDesc {
+    #[serde(default = "default_turbidity")]
+    turbidity: f32,
+    #[serde(default = "default_sun_elevation")]
+    sun_elevation: f32,
+    #[serde(default)]
+    sun_azimuth: f32,
+    #[serde(default = "default_sun_intensity")]
+    sun_intensity: f32,
+}
+
+fn default_turbidity() -> f32 {
+    3.0
+}
+
+fn default_sun_elevation() -> f32 {
+    45.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDesc {
+    // Color base, como textura o como color fijo
+    texture: Option<Spanned<String>>,
+    normal_map: Option<Spanned<String>>,
+    #[serde(default = "default_diffuse")]
+    diffuse: [u8; 3],
+    #[serde(default = "default_roughness")]
+    roughness: f32,
+    #[serde(default)]
+    metallic: f32,
+    // Textura por cara; las caras que no aparecen usan `texture` y `normal_map`
+    faces: Option<FacesDesc>,
+    // Mapas de `[maps]` que multiplican a `roughness` y `metallic`
+    roughness_map: Option<Spanned<String>>,
+    metallic_map: Option<Spanned<String>>,
+    #[serde(default)]
+    transmission: f32,
+    #[serde(default = "default_refractive_index")]
+    refractive_index: f32,
+    #[serde(default)]
+    emission: [u8; 3],
+    // Multiplica a `emission`, que como color no pasa de 1; es la radiancia de la superficie y
+    // también la de la luz de área que se deriva de ella
+    #[serde(default = "default_emission_strength")]
+    emission_strength: f32,
+    // Textura de `[textures]` que multiplica a `emission`
+    emission_map: Option<Spanned<String>>,
+    // Desplazamiento UV por segundo
+    #[serde(default)]
+    scroll: [f32; 2],
+    // Absorción por unidad de distancia de los materiales transparentes, por canal
+    #[serde(default)]
+    absorption: [f32; 3],
+}
+
+// `side` vale para las cuatro caras laterales que no se nombren por separado
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FacesDesc {
+    top: Option<Spanned<FaceDesc>>,
+    bottom: Option<Spanned<FaceDesc>>,
+    north: Option<Spanned<FaceDesc>>,
+    south: Option<Spanned<FaceDesc>>,
+    east: Option<Spanned<FaceDesc>>,
+    west: Option<Spanned<FaceDesc>>,
+    side: Option<Spanned<FaceDesc>>,
+}
+
+impl FacesDesc {
+    fn get(&self, face: Face) -> Option<&Spanned<FaceDesc>> {
+        let specific = match face {
+            Face::Top => return self.top.as_ref(),
+            Face::Bottom => return self.bottom.as_ref(),
+            Face::North => &self.north,
+            Face::South => &self.south,
+            Face::East => &self.east,
+            Face::West => &self.west,
+        };
+        specific.as_ref().or(self.side.as_ref())
+    }
+}
+
+// Como las caras de los modelos de bloque de Minecraft: `uv` va de 0 a 16 con y hacia abajo
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FaceDesc {
+    texture: Spanned<String>,
+    normal_map: Option<Spanned<String>>,
+    #[serde(default = "default_face_uv")]
+    uv: [f32; 4],
+    #[serde(default)]
+    rotation: u32,
+}
+
+fn default_face_uv() -> [f32; 4] {
+    [0.0, 0.0, 16.0, 16.0]
+}
+
+fn default_roughness() -> f32 {
+    1.0
+}
+
+fn default_emission_strength() -> f32 {
+    1.0
+}
+
+// El de la mayoría de los dieléctricos (vidrio, plástico): refleja un 4 % de frente
+fn default_refractive_index() -> f32 {
+    1.5
+}
+
+// Igual que la sección `animation` de los `.mcmeta` de Minecraft: los tiempos van en ticks
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AnimationDesc {
+    #[serde(default = "default_frametime")]
+    frametime: u32,
+    // Por defecto los cuadros son cuadrados (alto = ancho de la imagen)
+    frame_height: Option<usize>,
+    frames: Option<Vec<FrameDesc>>,
+    #[serde(default)]
+    interpolate: bool,
+}
+
+fn default_frametime() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FrameDesc {
+    Index(usize),
+    Timed { index: usize, time: u32 },
+}
+
+const EMISSIVE_LIGHT_SAMPLES: u32 = 16;
+
+// Un tick de Minecraft dura 1/20 s
+const TICK: f32 = 1.0 / 20.0;
+
+fn default_diffuse() -> [u8; 3] {
+    [255, 0, 0]
+}
+
+// Qué campos usa cada tipo: `point` position; `directional` direction; `spot` position,
+// direction, angle y blend; `area` min y max (una caja) o corner, edge_u y edge_v (un panel)
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightDesc {
+    #[serde(default)]
+    kind: LightKind,
+    position: Option<[f32; 3]>,
+    direction: Option<[f32; 3]>,
+    // Medio ángulo del cono, en grados
+    #[serde(default = "default_spot_angle")]
+    angle: f32,
+    #[serde(default = "default_spot_blend")]
+    blend: f32,
+    min: Option<[f32; 3]>,
+    max: Option<[f32; 3]>,
+    corner: Option<[f32; 3]>,
+    edge_u: Option<[f32; 3]>,
+    edge_v: Option<[f32; 3]>,
+    // Muestras por punto de las luces de área; se redondea al cuadrado más cercano
+    #[serde(default = "default_light_samples")]
+    samples: u32,
+    color: [u8; 3],
+    intensity: f32,
+    // Solo se enciende de noche, como los bloques emisivos
+    #[serde(default)]
+    night: bool,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum LightKind {
+    #[default]
+    Point,
+    Directional,
+    Spot,
+    Area,
+}
+
+fn default_spot_angle() -> f32 {
+    30.0
+}
+
+fn default_spot_blend() -> f32 {
+    0.2
+}
+
+fn default_light_samples() -> u32 {
+    16
+}
+
+// Un bloque suelto (`at`) o una caja de bloques [from, to)
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BlockDesc {
+    material: Spanned<String>,
+    at: Option<[i32; 3]>,
+    from: Option<[i32; 3]>,
+    to: Option<[i32; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CubeDesc {
+    material: Spanned<String>,
+    min: [f32; 3],
+    max: [f32; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SphereDesc {
+    material: Spanned<String>,
+    center: [f32; 3],
+    radius: f32,
+}
+
+fn emissive_light(shape: AreaShape, material: &Material) -> Light {
+    Light::Area {
+        shape,
+        // La radiancia de la luz es la emisión media del material, con su mapa de emisión, la
+        // misma que se ve en promedio sobre su superficie
+        color: material.average_emission(),
+        intensity: 1.0,
+        samples: EMISSIVE_LIGHT_SAMPLES,
+    }
+}
+
+fn vec3(v: [f32; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn color(c: [u8; 3]) -> Color {
+    Color::from_srgb8(c[0], c[1], c[2])
+}
+
+// De dónde salen las texturas de los materiales: las declaradas una por una en la escena y los
+// tiles de la carpeta `tiles`, empaquetados en un atlas
+struct TextureSources {
+    textures: HashMap<String, Arc<Texture>>,
+    normal_maps: HashMap<String, Arc<Texture>>,
+    maps: HashMap<String, Arc<Texture>>,
+    tiles: Option<TextureRegistry>,
+}
+
+impl TextureSources {
+    fn texture(&self, name: &str) -> Option<TextureView> {
+        match self.textures.get(name) {
+            Some(texture) => Some(TextureView::whole(texture.clone())),
+            None => self.tiles.as_ref()?.texture(name),
+        }
+    }
+
+    fn data(&self, table: &HashMap<String, Arc<Texture>>, name: &str) -> Option<TextureView> {
+        match table.get(name) {
+            Some(texture) => Some(TextureView::whole(texture.clone())),
+            None => self.tiles.as_ref()?.data(name),
+        }
+    }
+}
+
+struct Loader<'a> {
+    path: &'a Path,
+    source: &'a str,
+}
+
+impl Loader<'_> {
+    fn error(&self, span: Option<Range<usize>>, message: impl Into<String>) -> SceneError {
+        SceneError {
+            path: self.path.to_path_buf(),
+            location: span.map(|span| line_column(self.source, span.start)),
+            message: message.into(),
+        }
+    }
+
+    // Las rutas son relativas al archivo de escena
+    fn resolve(&self, file: &Spanned<String>) -> PathBuf {
+        let base = self.path.parent().unwrap_or(Path::new(""));
+        base.join(file.get_ref())
+    }
+
+    fn load_texture(&self, file: &Spanned<String>) -> Result<Texture, SceneError> {
+        Texture::load(self.resolve(file))
+            .map_err(|e| self.error(Some(file.span()), format!("{}: {}", file.get_ref(), e)))
+    }
+
+    fn load_normal_map(&self, file: &Spanned<String>) -> Result<Arc<Texture>, SceneError> {
+        Texture::load_linear(self.resolve(file))
+            .map(Arc::new)
+            .map_err(|e| self.error(Some(file.span()), format!("{}: {}", file.get_ref(), e)))
+    }
+
+    // Error para una textura que un bloque nombra y no existe; con carpeta de tiles se dice qué
+    // archivo faltó
+    fn missing_texture(
+        &self,
+        block: &str,
+        name: &Spanned<String>,
+        kind: &str,
+        sources: &TextureSources,
+    ) -> SceneError {
+        let message = match &sources.tiles {
+            Some(tiles) => format!(
+                "block `{block}` uses {kind} `{}`, but {} does not exist",
+                name.get_ref(),
+                tiles.path_of(name.get_ref()).display()
+            ),
+            None => format!("block `{block}` uses unknown {kind} `{}`", name.get_ref()),
+        };
+        self.error(Some(name.span()), message)
+    }
+
+    fn material(
+        &self,
+        block: &str,
+        desc: &Spanned<MaterialDesc>,
+        sources: &TextureSources,
+    ) -> Result<Material, SceneError> {
+        let span = desc.span();
+        let desc = desc.get_ref();
+        for (field, value) in [
+            ("roughness", desc.roughness),
+            ("metallic", desc.metallic),
+            ("transmission", desc.transmission),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(self.error(
+                    Some(span),
+                    format!("`{field}` must be between 0 and 1, got {value}"),
+                ));
+            }
+        }
+        if desc.emission_strength < 0.0 {
+            return Err(self.error(
+                Some(span),
+                format!(
+                    "`emission_strength` must not be negative, got {}",
+                    desc.emission_strength
+                ),
+            ));
+        }
+        if desc.refractive_index < 1.0 {
+            return Err(self.error(
+                Some(span),
+                format!(
+                    "`refractive_index` must be at least 1, got {}",
+                    desc.refractive_index
+                ),
+            ));
+        }
+
+        let texture = |name: &Spanned<String>| {
+            sources
+                .texture(name.get_ref())
+                .ok_or_else(|| self.missing_texture(block, name, "texture", sources))
+        };
+        let normal_map = |name: &Spanned<String>| {
+            sources
+                .data(&sources.normal_maps, name.get_ref())
+                .ok_or_else(|| self.missing_texture(block, name, "normal map", sources))
+        };
+        let map = |name: &Spanned<String>| {
+            sources
+                .data(&sources.maps, name.get_ref())
+                .ok_or_else(|| self.missing_texture(block, name, "map", sources))
+        };
+
+        let emission = color(desc.emission) * desc.emission_strength;
+        let mut material = match &desc.texture {
+            Some(name) => Material::new_with_texture(
+                desc.roughness,
+                desc.metallic,
+                desc.refractive_index,
+                texture(name)?,
+                emission,
+            ),
+            None => Material::new(
+                color(desc.diffuse),
+                desc.roughness,
+                desc.metallic,
+                desc.refractive_index,
+                emission,
+            ),
+        };
+
+        if let Some(name) = &desc.normal_map {
+            material = material.with_normal_map(normal_map(name)?);
+        }
+        if let Some(faces) = &desc.faces {
+            let mut face_textures: [Option<FaceTexture>; 6] = Default::default();
+            for face in Face::ALL {
+                let Some(face_desc) = faces.get(face) else {
+                    continue;
+                };
+                let span = face_desc.span();
+                let face_desc = face_desc.get_ref();
+                if ![0, 90, 180, 270].contains(&face_desc.rotation) {
+                    return Err(self.error(
+                        Some(span),
+                        format!(
+                            "`rotation` must be 0, 90, 180 or 270, got {}",
+                            face_desc.rotation
+                        ),
+                    ));
+                }
+                if face_desc.uv.iter().any(|c| !(0.0..=16.0).contains(c)) {
+                    return Err(self.error(Some(span), "`uv` must be between 0 and 16"));
+                }
+
+                let mut face_texture = FaceTexture::new(texture(&face_desc.texture)?);
+                if let Some(name) = &face_desc.normal_map {
+                    face_texture.normal_map = Some(normal_map(name)?);
+                }
+                face_texture.uv = face_desc.uv;
+                face_texture.rotation = face_desc.rotation;
+                face_textures[face.index()] = Some(face_texture);
+            }
+            material = material.with_faces(face_textures);
+        }
+        if let Some(name) = &desc.roughness_map {
+            material = material.with_roughness_map(map(name)?);
+        }
+        if let Some(name) = &desc.metallic_map {
+            material = material.with_metallic_map(map(name)?);
+        }
+        if let Some(name) = &desc.emission_map {
+            material = material.with_emission_map(texture(name)?);
+        }
+        let [r, g, b] = desc.absorption;
+        Ok(material
+            .with_transmission(desc.transmission)
+            .with_scroll(desc.scroll)
+            .with_absorption(Color::new(r, g, b)))
+    }
+
+    fn animation(
+        &self,
+        desc: &Spanned<AnimationDesc>,
+        texture: &Texture,
+    ) -> Result<Animation, SceneError> {
+        let span = desc.span();
+        let desc = desc.get_ref();
+
+        let frame_height = desc.frame_height.unwrap_or(texture.width);
+        if frame_height == 0 || !texture.height.is_multiple_of(frame_height) {
+            return Err(self.error(
+                Some(span),
+                format!(
+                    "texture height {} is not a multiple of the frame height {}",
+                    texture.height, frame_height
+                ),
+            ));
+        }
+        let frame_count = texture.height / frame_height;
+
+        let frames: Vec<(usize, f32)> = match &desc.frames {
+            Some(frames) => frames
+                .iter()
+                .map(|frame| match *frame {
+                    FrameDesc::Index(index) => (index, desc.frametime as f32 * TICK),
+                    FrameDesc::Timed { index, time } => (index, time as f32 * TICK),
+                })
+                .collect(),
+            None => (0..frame_count)
+                .map(|index| (index, desc.frametime as f32 * TICK))
+                .collect(),
+        };
+        if let Some(&(index, _)) = frames.iter().find(|&&(index, _)| index >= frame_count) {
+            return Err(self.error(
+                Some(span),
+                format!("frame {index} is out of range (the texture has {frame_count} frames)"),
+            ));
+        }
+
+        Ok(Animation {
+            frame_height,
+            frames,
+            interpolate: desc.interpolate,
+        })
+    }
+
+    fn light(&self, desc: &Spanned<LightDesc>) -> Result<Light, SceneError> {
+        let span = desc.span();
+        let desc = desc.get_ref();
+        let require = |field: Option<[f32; 3]>, name: &str| {
+            field
+                .map(vec3)
+                .ok_or_else(|| self.error(Some(span.clone()), format!("this light needs `{name}`")))
+        };
+        let color = color(desc.color);
+        let intensity = desc.intensity;
+
+        Ok(match desc.kind {
+            LightKind::Point => Light::Point {
+                position: require(desc.position, "position")?,
+                color,
+                intensity,
+            },
+            LightKind::Directional => Light::Directional {
+                direction: require(desc.direction, "direction")?.normalize(),
+                color,
+                intensity,
+            },
+            LightKind::Spot => {
+                if !(0.0..=90.0).contains(&desc.angle) || !(0.0..=1.0).contains(&desc.blend) {
+                    return Err(self.error(
+                        Some(span),
+                        "a spot light needs an angle in [0, 90] and a blend in [0, 1]",
+                    ));
+                }
+                Light::Spot {
+                    position: require(desc.position, "position")?,
+                    direction: require(desc.direction, "direction")?.normalize(),
+                    angle: desc.angle.to_radians(),
+                    blend: desc.blend,
+                    color,
+                    intensity,
+                }
+            }
+            LightKind::Area => {
+                let shape = match (desc.min, desc.max, desc.corner, desc.edge_u, desc.edge_v) {
+                    (Some(min), Some(max), None, None, None) => AreaShape::Box {
+                        min: vec3(min),
+                        max: vec3(max),
+                    },
+                    (None, None, Some(corner), Some(edge_u), Some(edge_v)) => AreaShape::Rect {
+                        corner: vec3(corner),
+                        edge_u: vec3(edge_u),
+                        edge_v: vec3(edge_v),
+                    },
+                    _ => {
+                        return Err(self.error(
+                            Some(span),
+                            "an area light needs either `min` and `max` or `corner`, `edge_u` and `edge_v`",
+                        ))
+                    }
+                };
+                Light::Area {
+                    shape,
+                    color,
+                    intensity,
+                    samples: desc.samples,
+                }
+            }
+        })
+    }
+}
+
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+    (line, column)
+}
+
+pub fn load_scene(path: impl AsRef<Path>) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).map_err(|e| SceneError {
+        path: path.to_path_buf(),
+        location: None,
+        message: e.to_string(),
+    })?;
+    parse_scene(path, &source)
+}
+
+pub fn parse_scene(path: &Path, source: &str) -> Result<Scene, SceneError> {
+    let loader = Loader { path, source };
+
+    let file: SceneFile =
+        toml::from_str(source).map_err(|e| loader.error(e.span(), e.message()))?;
+
+    let mut files = vec![path.to_path_buf(), loader.resolve(&file.skybox)];
+    let skybox = Arc::new(loader.load_texture(&file.skybox)?);
+    let night_skybox = match &file.night_skybox {
+        Some(night_skybox) => {
+            files.push(loader.resolve(night_skybox));
+            Some(Arc::new(loader.load_texture(night_skybox)?))
+        }
+        None => None,
+    };
+
+    let mut textures = HashMap::new();
+    for (name, texture_path) in &file.textures {
+        files.push(loader.resolve(texture_path));
+        let mut texture = loader.load_texture(texture_path)?;
+        if let Some(animation) = file.animations.get(name) {
+            texture.animation = Some(loader.animation(animation, &texture)?);
+        }
+        textures.insert(name.clone(), Arc::new(texture));
+    }
+    for (name, animation) in &file.animations {
+        if !file.textures.contains_key(name) {
+            return Err(loader.error(
+                Some(animation.span()),
+                format!("animation for unknown texture `{name}`"),
+            ));
+        }
+    }
+
+    let mut normal_maps = HashMap::new();
+    for (name, normal_map_path) in &file.normal_maps {
+        files.push(loader.resolve(normal_map_path));
+        normal_maps.insert(name.clone(), loader.load_normal_map(normal_map_path)?);
+    }
+
+    let mut maps = HashMap::new();
+    for (name, map_path) in &file.maps {
+        files.push(loader.resolve(map_path));
+        maps.insert(name.clone(), loader.load_normal_map(map_path)?);
+    }
+
+    let mut world = World::new();
+    let mut materials: HashMap<String, Material> = HashMap::new();
+    let mut block_ids: HashMap<String, BlockId> = HashMap::new();
+
+    let tiles = match &file.tiles {
+        Some(folder) => {
+            let registry = TextureRegistry::load_folder(loader.resolve(folder))
+                .map_err(|e| loader.error(Some(folder.span()), e.to_string()))?;
+            files.extend(registry.files().iter().cloned());
+            Some(registry)
+        }
+        None => None,
+    };
+    let sources = TextureSources {
+        textures,
+        normal_maps,
+        maps,
+        tiles,
+    };
+
+    for (name, desc) in &file.materials {
+        let material = loader.material(name, desc, &sources)?;
+        block_ids.insert(name.clone(), world.register_block(material.clone()));
+        materials.insert(name.clone(), material);
+    }
+
+    for block in &file.blocks {
+        let span = block.span();
+        let block = block.get_ref();
+        let id = *block_ids.get(block.material.get_ref()).ok_or_else(|| {
+            loader.error(
+                Some(block.material.span()),
+                format!("unknown material `{}`", block.material.get_ref()),
+            )
+        })?;
+
+        match (block.at, block.from, block.to) {
+            (Some([x, y, z]), None, None) => world.set_block(x, y, z, id),
+            (None, Some(from), Some(to)) => world.fill(from, to, id),
+            _ => {
+                return Err(loader.error(
+                    Some(span),
+                    "a block needs either `at` or both `from` and `to`",
+                ))
+            }
+        }
+    }
+
+    let find_material = |name: &Spanned<String>| {
+        materials.get(name.get_ref()).cloned().ok_or_else(|| {
+            loader.error(
+                Some(name.span()),
+                format!("unknown material `{}`", name.get_ref()),
+            )
+        })
+    };
+
+    let mut shapes: Vec<Object> = Vec::new();
+    for cube in &file.cubes {
+        shapes.push(Arc::new(Cube {
+            min: vec3(cube.min),
+            max: vec3(cube.max),
+            material: find_material(&cube.material)?,
+        }));
+    }
+    for sphere in &file.spheres {
+        shapes.push(Arc::new(Sphere {
+            center: vec3(sphere.center),
+            radius: sphere.radius,
+            material: find_material(&sphere.material)?,
+        }));
+    }
+
+    let animated_materials = materials
+        .values()
+        .filter(|material| material.is_animated())
+        .cloned()
+        .collect();
+
+    let mut lights = Vec::new();
+    // Los bloques y primitivas emisivos iluminan como luces de área; se encienden con la
+    // emisión, de noche
+    let mut lamps: Vec<Light> = world
+        .cubes_where(|material| !material.emission.is_black())
+        .into_iter()
+        .map(|cube| {
+            emissive_light(
+                AreaShape::Box {
+                    min: cube.min,
+                    max: cube.max,
+                },
+                &cube.material,
+            )
+        })
+        .collect();
+    for cube in &file.cubes {
+        let material = find_material(&cube.material)?;
+        if !material.emission.is_black() {
+            let shape = AreaShape::Box {
+                min: vec3(cube.min),
+                max: vec3(cube.max),
+            };
+            lamps.push(emissive_light(shape, &material));
+        }
+    }
+    for sphere in &file.spheres {
+        let material = find_material(&sphere.material)?;
+        if !material.emission.is_black() {
+            let shape = AreaShape::Sphere {
+                center: vec3(sphere.center),
+                radius: sphere.radius,
+            };
+            lamps.push(emissive_light(shape, &material));
+        }
+    }
+    for desc in &file.lights {
+        let light = loader.light(desc)?;
+        if desc.get_ref().night {
+            lamps.push(light);
+        } else {
+            lights.push(light);
+        }
+    }
+
+    let day_cycle = file.day_cycle.as_ref().map(|desc| DayCycle {
+        hour: desc.hour.rem_euclid(24.0),
+        speed: desc.speed,
+        sun_intensity: desc.sun_intensity,
+        moon_intensity: desc.moon_intensity,
+    });
+
+    let sky = file.sky.as_ref().map(|desc| {
+        let elevation = desc.sun_elevation.to_radians();
+        let azimuth = desc.sun_azimuth.to_radians();
+        SkySettings {
+            turbidity: desc.turbidity,
+            sun_direction: Vec3::new(
+                azimuth.sin() * elevation.cos(),
+                elevation.sin(),
+                azimuth.cos() * elevation.cos(),
+            ),
+            sun_intensity: desc.sun_intensity,
+        }
+    });
+
+    let camera = Camera::new(
+        vec3(file.camera.eye),
+        vec3(file.camera.center),
+        vec3(file.camera.up),
+    );
+
+    Ok(Scene {
+        world: Arc::new(world),
+        shapes,
+        lights,
+        lamps,
+        camera,
+        skybox,
+        night_skybox,
+        day_cycle,
+        sky,
+        files,
+        animated_materials,
+    })
+}
+
+// Revisa periódicamente la fecha de modificación de los archivos de la escena.
+pub struct SceneWatcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    last_poll: Instant,
+}
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl SceneWatcher {
+    pub fn new(files: &[PathBuf]) -> Self {
+        let mut watcher = SceneWatcher {
+            files: Vec::new(),
+            last_poll: Instant::now(),
+        };
+        watcher.watch(files);
+        watcher
+    }
+
+    pub fn watch(&mut self, files: &[PathBuf]) {
+        self.files = files.iter().map(|f| (f.clone(), modified(f))).collect();
+    }
+
+    // true si algún archivo cambió desde la última revisión
+    pub fn poll(&mut self) -> bool {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+
+        let mut changed = false;
+        for (path, last_modified) in &mut self.files {
+            let current = modified(path);
+            if current != *last_modified {
+                *last_modified = current;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Lo mínimo para que una escena cargue; el resto de cada prueba empieza en la línea 6
+    const HEADER: &str = r#"skybox = "../assets/skybox.png"
+[camera]
+eye = [0.0, 0.0, -5.0]
+center = [0.0, 0.0, 0.0]
+
+"#;
+
+    fn load_error(body: &str) -> String {
+        match parse_scene(Path::new("scenes/test.toml"), &format!("{HEADER}{body}")) {
+            Ok(_) => panic!("scene should not load"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn loads_header() {
+        assert!(parse_scene(Path::new("scenes/test.toml"), HEADER).is_ok());
+    }
+
+    #[test]
+    fn block_ids_follow_material_names() {
+        let body = r#"[materials.zeta]
+diffuse = [128, 128, 128]
+
+[materials.alpha]
+diffuse = [64, 64, 64]
+
+[[blocks]]
+material = "zeta"
+at = [0, 0, 0]
+
+[[blocks]]
+material = "alpha"
+at = [1, 0, 0]
+"#;
+        let scene = parse_scene(Path::new("scenes/test.toml"), &format!("{HEADER}{body}"))
+            .expect("scene should load");
+        assert_eq!(scene.world.get_block(1, 0, 0), 1);
+        assert_eq!(scene.world.get_block(0, 0, 0), 2);
+    }
+
+    #[test]
+    fn sun_light_matches_sky_disc() {
+        let body = r#"[day_cycle]
+hour = 6.5
+
+[sky]
+turbidity = 3.0
+"#;
+        let scene = parse_scene(Path::new("scenes/test.toml"), &format!("{HEADER}{body}"))
+            .expect("scene should load");
+        let (_, disc) = scene.environment().sun.expect("the sky draws a sun");
+        match scene.lights().first() {
+            Some(Light::Directional { color, .. }) => assert_eq!(*color, disc),
+            light => panic!("expected the sun light but got {light:?}"),
+        }
+    }
+
+    #[test]
+    fn unknown_material() {
+        let error = load_error(
+            r#"[materials.stone]
+diffuse = [128, 128, 128]
+
+[[blocks]]
+material = "stonr"
+at = [0, 0, 0]
+"#,
+        );
+        assert_eq!(error, "scenes/test.toml:10:12: unknown material `stonr`");
+    }
+
+    #[test]
+    fn invalid_face_rotation() {
+        let error = load_error(
+            r#"[materials.stone]
+faces.top = { texture = "stone", rotation = 45 }
+"#,
+        );
+        assert_eq!(
+            error,
+            "scenes/test.toml:7:13: `rotation` must be 0, 90, 180 or 270, got 45"
+        );
+    }
+
+    #[test]
+    fn area_light_without_max() {
+        let error = load_error(
+            r#"[[lights]]
+kind = "area"
+min = [0.0, 0.0, 0.0]
+color = [255, 255, 255]
+intensity = 1.0
+"#,
+        );
+        assert_eq!(
+            error,
+            "scenes/test.toml:6:1: an area light needs either `min` and `max` or `corner`, `edge_u` and `edge_v`"
+        );
+    }
+
+    #[test]
+    fn missing_texture_file() {
+        let error = load_error(
+            r#"[textures]
+stone = "../assets/stone.png"
+"#,
+        );
+        assert!(
+            error.starts_with("scenes/test.toml:7:9: ../assets/stone.png: "),
+            "{error}"
+        );
+    }
+}
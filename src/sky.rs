@@ -0,0 +1,146 @@
+use crate::color::Color;
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+// Pasa la luminancia del modelo (kcd/m²) a la escala de radiancia del resto de la escena
+const SKY_SCALE: f32 = 0.05;
+
+// Modelo analítico de cielo de Preetham, Shirley y Smits (1999): el color depende de la
+// dirección del sol y de la turbidez (2 = cielo muy limpio, 10 = brumoso).
+#[derive(Debug, Clone)]
+pub struct Sky {
+    pub sun_direction: Vec3,
+    pub turbidity: f32,
+    // Luminancia Y y cromaticidades x, y en el cenit
+    zenith: [f32; 3],
+    // Coeficientes A..E de la función de Perez para Y, x e y
+    perez: [[f32; 5]; 3],
+    // F(0, θs) de cada canal, para normalizar contra el cenit
+    perez_zenith: [f32; 3],
+}
+
+fn perez_function(coeffs: &[f32; 5], cos_theta: f32, gamma: f32) -> f32 {
+    let [a, b, c, d, e] = *coeffs;
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+impl Sky {
+    pub fn new(sun_direction: Vec3, turbidity: f32) -> Self {
+        let sun_direction = sun_direction.normalize();
+        let t = turbidity;
+        // El modelo no está definido con el sol bajo el horizonte
+        let theta_s = sun_direction.y.clamp(0.0, 1.0).acos().min(PI / 2.0 - 0.01);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let theta = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+        let turbidity_terms = [t * t, t, 1.0];
+        let chromaticity = |m: [[f32; 4]; 3]| -> f32 {
+            (0..3)
+                .map(|i| {
+                    let row: f32 = (0..4).map(|j| m[i][j] * theta[j]).sum();
+                    turbidity_terms[i] * row
+                })
+                .sum()
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_chroma_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+        let perez_zenith = [
+            perez_function(&perez[0], 1.0, theta_s),
+            perez_function(&perez[1], 1.0, theta_s),
+            perez_function(&perez[2], 1.0, theta_s),
+        ];
+
+        Sky {
+            sun_direction,
+            turbidity,
+            zenith: [zenith_y, zenith_x, zenith_chroma_y],
+            perez,
+            perez_zenith,
+        }
+    }
+
+    // Radiancia del cielo en una dirección. La isla flota, así que bajo el horizonte también hay
+    // cielo: se refleja el hemisferio superior.
+    pub fn radiance(&self, direction: &Vec3) -> Color {
+        let direction = direction.normalize();
+        let cos_theta = direction.y.abs().max(0.01);
+        let gamma = direction.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let channel = |i: usize| {
+            self.zenith[i] * perez_function(&self.perez[i], cos_theta, gamma) / self.perez_zenith[i]
+        };
+        let luminance = channel(0).max(0.0) * SKY_SCALE;
+        let x = channel(1);
+        let y = channel(2);
+
+        xyy_to_rgb(x, y, luminance)
+    }
+
+    // Color de la luz directa del sol después de atravesar la atmósfera (dispersión de
+    // Rayleigh y aerosoles, apéndice del artículo de Preetham)
+    pub fn sun_color(&self) -> Color {
+        let elevation = self.sun_direction.y.clamp(0.0, 1.0);
+        let theta_s_deg = elevation.acos().to_degrees();
+        let relative_mass =
+            1.0 / (elevation + 0.15 * (93.885 - theta_s_deg).max(0.01).powf(-1.253));
+
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let transmittance = |lambda_um: f32| {
+            let rayleigh = (-0.008735 * lambda_um.powf(-4.08) * relative_mass).exp();
+            let aerosol = (-beta * lambda_um.powf(-1.3) * relative_mass).exp();
+            rayleigh * aerosol
+        };
+        Color::new(
+            transmittance(0.68),
+            transmittance(0.55),
+            transmittance(0.44),
+        )
+    }
+}
+
+fn xyy_to_rgb(x: f32, y: f32, luminance: f32) -> Color {
+    if y <= 0.0 {
+        return Color::black();
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Color::new(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
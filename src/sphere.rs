@@ -1,6 +1,8 @@
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::ray_intersect::{Intersect, RayIntersect};
 use nalgebra_glm::{dot, Vec3};
+use std::f32::consts::PI;
 
 pub struct Sphere {
     pub center: Vec3,
@@ -11,10 +13,21 @@ pub struct Sphere {
 impl Sphere {
     fn get_uv(&self, point: &Vec3) -> (f32, f32) {
         let normalized = (point - self.center) / self.radius;
-        let u = 0.5 + (normalized.z.atan2(normalized.x) / (2.0 * std::f32::consts::PI));
-        let v = 0.5 - (normalized.y.asin() / std::f32::consts::PI);
+        let u = 0.5 + (normalized.z.atan2(normalized.x) / (2.0 * PI));
+        let v = 0.5 - (normalized.y.asin() / PI);
         (u, v)
     }
+
+    // u crece con el ángulo alrededor del eje y; v crece hacia el polo sur
+    fn get_tangents(&self, normal: &Vec3) -> (Vec3, Vec3) {
+        let tangent = Vec3::new(-normal.z, 0.0, normal.x);
+        if tangent.magnitude_squared() < 1e-8 {
+            // En los polos la base no está definida
+            return (Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0));
+        }
+        let tangent = tangent.normalize();
+        (tangent, normal.cross(&tangent))
+    }
 }
 
 impl RayIntersect for Sphere {
@@ -31,18 +44,31 @@ impl RayIntersect for Sphere {
         let discriminant = b * b - 4.0 * a * c;
 
         if discriminant > 0.0 {
-            let t = (-b - discriminant.sqrt()) / (2.0 * a);
+            // Si el rayo nace dentro de la esfera choca con la salida
+            let near = (-b - discriminant.sqrt()) / (2.0 * a);
+            let far = (-b + discriminant.sqrt()) / (2.0 * a);
+            let t = if near > 0.0 { near } else { far };
             if t > 0.0 {
                 let point = ray_origin + ray_direction * t;
                 let normal = (point - self.center).normalize();
                 let distance = t;
                 let (u, v) = self.get_uv(&point);
+                let (tangent, bitangent) = self.get_tangents(&normal);
+                // u da una vuelta entera al paralelo del punto y v va de polo a polo
+                let parallel = 2.0 * PI * self.radius * (1.0 - normal.y * normal.y).max(0.0).sqrt();
 
-                return Intersect::new(point, normal, distance, self.material.clone(), u, v);
+                return Intersect::new(point, normal, distance, self.material.clone(), u, v)
+                    .with_tangents(tangent, bitangent)
+                    .with_uv_scale(parallel, PI * self.radius);
             }
         }
 
         // If no intersection, return an empty intersect
         Intersect::empty()
     }
+
+    fn aabb(&self) -> Aabb {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::new(self.center - extent, self.center + extent)
+    }
 }
@@ -1,60 +1,307 @@
 extern crate image;
 use crate::color::Color;
-use image::{DynamicImage, GenericImageView, ImageReader, Pixel};
+use crate::cycle;
+use clap::ValueEnum;
+use image::{DynamicImage, ImageError, ImageReader};
 use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct Texture {
-    image: DynamicImage,
     pub width: usize,
     pub height: usize,
-    color_array: Vec<Color>,
+    // Cadena de mipmaps: el nivel 0 es la imagen y cada uno de los siguientes mide la mitad
+    levels: Vec<Level>,
+    pub animation: Option<Animation>,
+}
+
+// Un nivel de la cadena, fila por fila empezando por arriba
+#[derive(Clone)]
+struct Level {
+    width: usize,
+    height: usize,
+    colors: Vec<Color>,
+}
+
+impl Level {
+    // Promedio de cada bloque de 2x2 texels; en un lado impar el último texel se repite
+    fn downsample(&self) -> Level {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut colors = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let (x0, y0) = (2 * x, 2 * y);
+                let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+                let sum = self.colors[y0 * self.width + x0]
+                    + self.colors[y0 * self.width + x1]
+                    + self.colors[y1 * self.width + x0]
+                    + self.colors[y1 * self.width + x1];
+                colors.push(sum * 0.25);
+            }
+        }
+        Level {
+            width,
+            height,
+            colors,
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> Color {
+        self.colors[y * self.width + x]
+    }
+}
+
+// Cómo se leen los texels de una textura
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TextureFilter {
+    // El texel más cercano de la imagen completa
+    Nearest,
+    // Interpolación entre los cuatro texels más cercanos de la imagen completa
+    Bilinear,
+    // De cerca, el texel más cercano (el pixel art se ve nítido); de lejos, mezcla entre los dos
+    // mipmaps que corresponden al tamaño del píxel en la textura, bilineal a partir del nivel 1
+    Trilinear,
+}
+
+impl fmt::Display for TextureFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        cycle::write_name(self, f)
+    }
+}
+
+// Todo lo que hace falta para leer una textura en un punto además de (u, v): el instante de las
+// animaciones (en segundos), el filtro y la huella del píxel, es decir cuánto cambian u y v
+// de un píxel al de al lado
+#[derive(Debug, Clone, Copy)]
+pub struct TextureLookup {
+    pub time: f32,
+    pub filter: TextureFilter,
+    pub footprint: (f32, f32),
+}
+
+impl TextureLookup {
+    // Sin huella: siempre el nivel 0
+    pub fn at(time: f32, filter: TextureFilter) -> Self {
+        TextureLookup {
+            time,
+            filter,
+            footprint: (0.0, 0.0),
+        }
+    }
+}
+
+// Tira vertical de cuadros al estilo de Minecraft: los cuadros van uno debajo del otro y se
+// recorren en el orden de `frames`.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub frame_height: usize,
+    // (índice del cuadro, duración en segundos)
+    pub frames: Vec<(usize, f32)>,
+    // Mezcla cada cuadro con el siguiente en lugar de cambiar de golpe
+    pub interpolate: bool,
+}
+
+impl Animation {
+    // Cuadro actual, cuadro siguiente y qué tanto se avanzó hacia él
+    fn frame_at(&self, time: f32) -> (usize, usize, f32) {
+        let duration: f32 = self.frames.iter().map(|&(_, d)| d).sum();
+        if self.frames.is_empty() || duration <= 0.0 {
+            return (0, 0, 0.0);
+        }
+
+        let mut t = time.rem_euclid(duration);
+        for (i, &(frame, frame_duration)) in self.frames.iter().enumerate() {
+            if t < frame_duration || i == self.frames.len() - 1 {
+                let next = self.frames[(i + 1) % self.frames.len()].0;
+                return (frame, next, (t / frame_duration).min(1.0));
+            }
+            t -= frame_duration;
+        }
+        unreachable!()
+    }
+
+    // Lo que se ve en un instante: sin interpolación solo importa el cuadro
+    fn state_at(&self, time: f32) -> (usize, usize, f32) {
+        let (frame, next, blend) = self.frame_at(time);
+        if self.interpolate && next != frame {
+            (frame, next, blend)
+        } else {
+            (frame, frame, 0.0)
+        }
+    }
+
+    pub fn changes_between(&self, from: f32, to: f32) -> bool {
+        self.state_at(from) != self.state_at(to)
+    }
 }
 
 impl Texture {
-    pub fn new(file_path: &str) -> Texture {
-        let img = ImageReader::open(file_path)
-            .expect("Failed to open file")
-            .decode()
-            .expect("Failed to decode image");
+    pub fn load(file_path: impl AsRef<Path>) -> Result<Texture, ImageError> {
+        Texture::load_with(file_path, true)
+    }
+
+    // Para mapas de normales y otros datos que no son color: los valores se usan tal cual
+    pub fn load_linear(file_path: impl AsRef<Path>) -> Result<Texture, ImageError> {
+        Texture::load_with(file_path, false)
+    }
 
+    fn load_with(file_path: impl AsRef<Path>, srgb: bool) -> Result<Texture, ImageError> {
+        let img = ImageReader::open(file_path)?.decode()?;
         let width = img.width() as usize;
         let height = img.height() as usize;
 
-        assert!(
-            width > 0 && height > 0,
-            "Image is empty or not loaded correctly"
-        );
+        if width == 0 || height == 0 {
+            return Err(ImageError::Limits(image::error::LimitError::from_kind(
+                image::error::LimitErrorKind::DimensionError,
+            )));
+        }
 
-        let mut texture = Texture {
-            image: img,
+        Ok(Texture::from_colors(
             width,
             height,
-            color_array: vec![Color::black(); width * height],
-        };
-        texture.load_color_array();
-        texture
+            decode_colors(&img, srgb),
+        ))
     }
 
-    fn load_color_array(&mut self) {
-        for x in 0..self.width {
-            for y in 0..self.height {
-                let pixel = self.image.get_pixel(x as u32, y as u32).to_rgb();
-                let color =
-                    ((pixel[0] as u32) << 16) | ((pixel[1] as u32) << 8) | (pixel[2] as u32);
-                self.color_array[y * self.width + x] = Color::from_hex(color);
+    // `color_array` va fila por fila, empezando por arriba; los mipmaps llegan hasta 1x1
+    pub fn from_colors(width: usize, height: usize, color_array: Vec<Color>) -> Texture {
+        Texture::with_levels(width, height, color_array, usize::MAX)
+    }
+
+    // Como `from_colors`, pero con a lo sumo `count` niveles en la cadena de mipmaps
+    pub(crate) fn with_levels(
+        width: usize,
+        height: usize,
+        color_array: Vec<Color>,
+        count: usize,
+    ) -> Texture {
+        assert_eq!(
+            color_array.len(),
+            width * height,
+            "texture data does not match its size"
+        );
+        let mut levels = vec![Level {
+            width,
+            height,
+            colors: color_array,
+        }];
+        while levels.len() < count {
+            let last = &levels[levels.len() - 1];
+            if last.width == 1 && last.height == 1 {
+                break;
             }
+            levels.push(last.downsample());
+        }
+        Texture {
+            width,
+            height,
+            levels,
+            animation: None,
         }
     }
 
     pub fn get_color(&self, x: usize, y: usize) -> Color {
         if x < self.width && y < self.height {
-            self.color_array[y * self.width + x]
+            self.levels[0].get(x, y)
         } else {
             Color::from_hex(0xFF00FF) // Magenta para indicar error de coordenadas
         }
     }
 
+    // Niveles que se pueden usar; en una animación cada cuadro tiene que seguir ocupando un
+    // número entero de filas
+    fn level_count(&self) -> usize {
+        match &self.animation {
+            Some(animation) => self
+                .levels
+                .len()
+                .min(animation.frame_height.trailing_zeros() as usize + 1),
+            None => self.levels.len(),
+        }
+    }
+
+    // Color en (u, v) dentro de `rect`, del cuadro que toca mostrar en `lookup.time`. Los texels
+    // de fuera del rectángulo no se leen, así que los tiles de un atlas no se mezclan.
+    pub fn sample(&self, rect: &UvRect, u: f32, v: f32, lookup: &TextureLookup) -> Color {
+        let u = u.clamp(0.0, 1.0);
+        let v = v.clamp(0.0, 1.0);
+
+        let Some(animation) = &self.animation else {
+            return self.filtered(rect, u, v, 0, self.height, lookup);
+        };
+        let frame_height = animation.frame_height;
+        let (frame, next, blend) = animation.frame_at(lookup.time);
+        let color = self.filtered(rect, u, v, frame, frame_height, lookup);
+        if animation.interpolate && next != frame {
+            let next_color = self.filtered(rect, u, v, next, frame_height, lookup);
+            color * (1.0 - blend) + next_color * blend
+        } else {
+            color
+        }
+    }
+
+    // Color medio de `rect`, de todos los cuadros si es una animación
+    pub fn average(&self, rect: &UvRect) -> Color {
+        let region = Region::new(&self.levels[0], rect, 0, self.height, 0);
+        let left = region.left.round() as usize;
+        let top = region.top.round() as usize;
+        let right = ((region.left + region.width).round() as usize).max(left + 1);
+        let bottom = ((region.top + region.height).round() as usize).max(top + 1);
+        let mut sum = Color::black();
+        for y in top..bottom {
+            for x in left..right {
+                sum = sum + region.get(x as f32, y as f32);
+            }
+        }
+        sum * (1.0 / ((right - left) * (bottom - top)) as f32)
+    }
+
+    fn filtered(
+        &self,
+        rect: &UvRect,
+        u: f32,
+        v: f32,
+        frame: usize,
+        frame_height: usize,
+        lookup: &TextureLookup,
+    ) -> Color {
+        let region = |level| Region::new(&self.levels[level], rect, frame, frame_height, level);
+        match lookup.filter {
+            TextureFilter::Nearest => region(0).nearest(u, v),
+            TextureFilter::Bilinear => region(0).bilinear(u, v),
+            TextureFilter::Trilinear => {
+                // Cuántos texels del nivel 0 cubre el píxel; cada nivel los divide por dos
+                let (du, dv) = lookup.footprint;
+                let texels = (du * (rect.u1 - rect.u0) * self.width as f32)
+                    .max(dv * (rect.v1 - rect.v0) * frame_height as f32);
+                if texels.is_nan() || texels <= 1.0 {
+                    return region(0).nearest(u, v);
+                }
+
+                // El nivel 0 se lee sin suavizar, como de cerca; así el paso a los mipmaps es
+                // gradual y no deja una línea donde el píxel llega a cubrir un texel
+                let level = |level| {
+                    if level == 0 {
+                        region(0).nearest(u, v)
+                    } else {
+                        region(level).bilinear(u, v)
+                    }
+                };
+                let lod = texels.log2().min((self.level_count() - 1) as f32);
+                let lower = lod.floor() as usize;
+                let blend = lod - lower as f32;
+                let color = level(lower);
+                if blend > 0.0 {
+                    color * (1.0 - blend) + level(lower + 1) * blend
+                } else {
+                    color
+                }
+            }
+        }
+    }
+
     pub fn width(&self) -> usize {
         self.width
     }
@@ -64,6 +311,150 @@ impl Texture {
     }
 }
 
+// Las imágenes de color vienen en sRGB; el sombreado trabaja en RGB lineal
+pub(crate) fn decode_colors(img: &DynamicImage, srgb: bool) -> Vec<Color> {
+    img.to_rgb8()
+        .pixels()
+        .map(|pixel| {
+            if srgb {
+                Color::from_srgb8(pixel[0], pixel[1], pixel[2])
+            } else {
+                Color::new(
+                    pixel[0] as f32 / 255.0,
+                    pixel[1] as f32 / 255.0,
+                    pixel[2] as f32 / 255.0,
+                )
+            }
+        })
+        .collect()
+}
+
+// El rectángulo de un cuadro en un nivel de la cadena, en texels
+struct Region<'a> {
+    level: &'a Level,
+    left: f32,
+    top: f32,
+    width: f32,
+    height: f32,
+}
+
+impl<'a> Region<'a> {
+    fn new(
+        level: &'a Level,
+        rect: &UvRect,
+        frame: usize,
+        frame_height: usize,
+        index: usize,
+    ) -> Self {
+        let rows = (frame_height >> index).max(1) as f32;
+        let columns = level.width as f32;
+        let top = frame as f32 * rows + (1.0 - rect.v1) * rows;
+        Region {
+            level,
+            left: rect.u0 * columns,
+            top,
+            width: (rect.u1 - rect.u0) * columns,
+            height: (rect.v1 - rect.v0) * rows,
+        }
+    }
+
+    // Texel (x, y) llevado al borde del rectángulo si cae afuera
+    fn get(&self, x: f32, y: f32) -> Color {
+        let clamp = |value: f32, start: f32, length: f32| {
+            let first = start.round();
+            let last = (start + length).round() - 1.0;
+            value.clamp(first, last.max(first)) as usize
+        };
+        let x = clamp(x, self.left, self.width).min(self.level.width - 1);
+        let y = clamp(y, self.top, self.height).min(self.level.height - 1);
+        self.level.get(x, y)
+    }
+
+    // Posición de (u, v) en texels (v hacia arriba, las filas hacia abajo)
+    fn position(&self, u: f32, v: f32) -> (f32, f32) {
+        (
+            self.left + u * self.width,
+            self.top + (1.0 - v) * self.height,
+        )
+    }
+
+    fn nearest(&self, u: f32, v: f32) -> Color {
+        let (x, y) = self.position(u, v);
+        self.get(x.floor(), y.floor())
+    }
+
+    fn bilinear(&self, u: f32, v: f32) -> Color {
+        let (x, y) = self.position(u, v);
+        // Los centros de los texels están en las mitades
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let top = self.get(x0, y0) * (1.0 - tx) + self.get(x0 + 1.0, y0) * tx;
+        let bottom = self.get(x0, y0 + 1.0) * (1.0 - tx) + self.get(x0 + 1.0, y0 + 1.0) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+// Una textura entera o un tile dentro de un atlas
+#[derive(Debug, Clone)]
+pub struct TextureView {
+    pub texture: Arc<Texture>,
+    pub rect: UvRect,
+}
+
+impl TextureView {
+    pub fn whole(texture: Arc<Texture>) -> Self {
+        TextureView {
+            texture,
+            rect: UvRect::FULL,
+        }
+    }
+
+    pub fn sample(&self, u: f32, v: f32, lookup: &TextureLookup) -> Color {
+        self.texture.sample(&self.rect, u, v, lookup)
+    }
+
+    pub fn average(&self) -> Color {
+        self.texture.average(&self.rect)
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.texture.animation.is_some()
+    }
+
+    pub fn changes_between(&self, from: f32, to: f32) -> bool {
+        self.texture
+            .animation
+            .as_ref()
+            .is_some_and(|animation| animation.changes_between(from, to))
+    }
+}
+
+// Dos vistas son iguales si leen el mismo rectángulo de la misma textura cargada
+impl PartialEq for TextureView {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.texture, &other.texture) && self.rect == other.rect
+    }
+}
+
+// Rectángulo dentro de una textura, en UV (v hacia arriba)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvRect {
+    pub u0: f32,
+    pub v0: f32,
+    pub u1: f32,
+    pub v1: f32,
+}
+
+impl UvRect {
+    pub const FULL: UvRect = UvRect {
+        u0: 0.0,
+        v0: 0.0,
+        u1: 1.0,
+        v1: 1.0,
+    };
+}
+
 impl fmt::Debug for Texture {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Texture")
@@ -72,3 +463,32 @@ impl fmt::Debug for Texture {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation(interpolate: bool) -> Animation {
+        Animation {
+            frame_height: 16,
+            frames: vec![(0, 1.0), (1, 1.0)],
+            interpolate,
+        }
+    }
+
+    #[test]
+    fn changes_only_on_new_frame() {
+        let animation = animation(false);
+        assert!(!animation.changes_between(0.1, 0.9));
+        assert!(animation.changes_between(0.9, 1.1));
+        // Una vuelta completa deja el mismo cuadro
+        assert!(!animation.changes_between(0.5, 2.5));
+    }
+
+    #[test]
+    fn interpolated_changes_while_blending() {
+        let animation = animation(true);
+        assert!(animation.changes_between(0.1, 0.2));
+        assert!(!animation.changes_between(0.5, 0.5));
+    }
+}
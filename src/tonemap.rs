@@ -0,0 +1,94 @@
+use crate::color::Color;
+use crate::cycle;
+use clap::ValueEnum;
+use std::fmt;
+
+// Curva que lleva la radiancia lineal (sin límite) al rango [0, 1] de la pantalla
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Operator {
+    // Recorte simple, como antes de tener tone mapping
+    Clamp,
+    Reinhard,
+    Aces,
+    Filmic,
+}
+
+impl Operator {
+    fn map(self, x: f32) -> f32 {
+        match self {
+            Operator::Clamp => x,
+            Operator::Reinhard => x / (1.0 + x),
+            // Ajuste de Narkowicz a la curva ACES
+            Operator::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+            // Curva de Hable (Uncharted 2), normalizada con un punto blanco de 11.2
+            Operator::Filmic => hable(2.0 * x) / hable(11.2),
+        }
+    }
+}
+
+fn hable(x: f32) -> f32 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        cycle::write_name(self, f)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ToneMapping {
+    pub operator: Operator,
+    // Exposición en pasos (EV): cada paso duplica o divide a la mitad la luz
+    pub exposure: f32,
+    // Ajusta la exposición para que la luminancia media del cuadro quede en gris medio
+    pub auto_exposure: bool,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping {
+            operator: Operator::Aces,
+            exposure: 0.0,
+            auto_exposure: false,
+        }
+    }
+}
+
+const MIDDLE_GRAY: f32 = 0.18;
+
+impl ToneMapping {
+    // Factor por el que se multiplica la radiancia antes de aplicar la curva
+    pub fn scale(&self, radiance: &[Color]) -> f32 {
+        let mut scale = 2.0f32.powf(self.exposure);
+        if self.auto_exposure {
+            let key = log_average_luminance(radiance);
+            if key > 0.0 {
+                scale *= MIDDLE_GRAY / key;
+            }
+        }
+        scale
+    }
+
+    pub fn apply(&self, color: Color, scale: f32) -> Color {
+        let color = color * scale;
+        Color::new(
+            self.operator.map(color.r.max(0.0)),
+            self.operator.map(color.g.max(0.0)),
+            self.operator.map(color.b.max(0.0)),
+        )
+    }
+}
+
+// Media geométrica de la luminancia; el delta evita log(0) en los píxeles negros
+pub fn log_average_luminance(radiance: &[Color]) -> f32 {
+    if radiance.is_empty() {
+        return 0.0;
+    }
+    let sum: f32 = radiance
+        .iter()
+        .map(|color| (1e-4 + color.luminance().max(0.0)).ln())
+        .sum();
+    (sum / radiance.len() as f32).exp()
+}
@@ -0,0 +1,238 @@
+use crate::aabb::Aabb;
+use crate::cube::{face_intersect, Cube};
+use crate::material::Material;
+use crate::ray_intersect::{Intersect, RayIntersect};
+use nalgebra_glm::Vec3;
+
+pub type BlockId = u16;
+pub const AIR: BlockId = 0;
+
+// Mundo de bloques de tamaño fijo; cada celda unitaria guarda el id de su bloque.
+pub struct VoxelGrid {
+    pub origin: Vec3,
+    pub size: [usize; 3],
+    cells: Vec<BlockId>,
+    palette: Vec<Material>,
+}
+
+impl VoxelGrid {
+    pub fn new(origin: Vec3, size_x: usize, size_y: usize, size_z: usize) -> Self {
+        VoxelGrid {
+            origin,
+            size: [size_x, size_y, size_z],
+            cells: vec![AIR; size_x * size_y * size_z],
+            // El id 0 es aire, nunca se dibuja
+            palette: vec![Material::black()],
+        }
+    }
+
+    // Agrupa los cubos unitarios existentes en una cuadrícula; los cubos con el mismo material
+    // comparten id.
+    pub fn from_cubes(cubes: &[Cube]) -> Self {
+        let bounds = cubes
+            .iter()
+            .fold(Aabb::empty(), |acc, cube| acc.union(&cube.aabb()));
+        if cubes.is_empty() {
+            return VoxelGrid::new(Vec3::new(0.0, 0.0, 0.0), 0, 0, 0);
+        }
+
+        let origin = bounds.min.map(|c| c.floor());
+        let extent = (bounds.max - origin).map(|c| c.ceil().max(0.0) as usize);
+        let mut grid = VoxelGrid::new(origin, extent.x, extent.y, extent.z);
+
+        for cube in cubes {
+            let block = grid.find_or_register(&cube.material);
+            let min = (cube.min - origin).map(|c| c.round() as usize);
+            let max = (cube.max - origin).map(|c| c.round() as usize);
+            for x in min.x..max.x {
+                for y in min.y..max.y {
+                    for z in min.z..max.z {
+                        grid.set(x, y, z, block);
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+
+    pub fn register_block(&mut self, material: Material) -> BlockId {
+        let block = BlockId::try_from(self.palette.len()).expect("too many block types");
+        self.palette.push(material);
+        block
+    }
+
+    fn find_or_register(&mut self, material: &Material) -> BlockId {
+        let existing = self.palette.iter().skip(1).position(|m| m == material);
+        match existing {
+            Some(index) => (index + 1) as BlockId,
+            None => self.register_block(material.clone()),
+        }
+    }
+
+    pub fn material(&self, block: BlockId) -> &Material {
+        &self.palette[block as usize]
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.size[1] + y) * self.size[0] + x
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> BlockId {
+        if x < self.size[0] && y < self.size[1] && z < self.size[2] {
+            self.cells[self.index(x, y, z)]
+        } else {
+            AIR
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, block: BlockId) {
+        if x < self.size[0] && y < self.size[1] && z < self.size[2] {
+            let index = self.index(x, y, z);
+            self.cells[index] = block;
+        }
+    }
+}
+
+impl RayIntersect for VoxelGrid {
+    // Recorrido 3D-DDA (Amanatides–Woo) celda por celda
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        if self.cells.is_empty() {
+            return Intersect::empty();
+        }
+
+        let inv_dir = Vec3::new(
+            1.0 / ray_direction.x,
+            1.0 / ray_direction.y,
+            1.0 / ray_direction.z,
+        );
+        let bounds = self.aabb();
+        let Some(t_enter) = bounds.hit(ray_origin, &inv_dir, f32::INFINITY) else {
+            return Intersect::empty();
+        };
+
+        // Cara por la que entra el rayo a la cuadrícula, si empieza afuera
+        let mut hit_axis = None;
+        if t_enter > 0.0 {
+            let near = (bounds.min - ray_origin)
+                .component_mul(&inv_dir)
+                .inf(&(bounds.max - ray_origin).component_mul(&inv_dir));
+            hit_axis = Some(if near.x >= near.y && near.x >= near.z {
+                0
+            } else if near.y >= near.z {
+                1
+            } else {
+                2
+            });
+        }
+
+        let mut t = t_enter.max(0.0);
+        let start = ray_origin + ray_direction * t - self.origin;
+
+        let mut cell = [0i64; 3];
+        let mut step = [0i64; 3];
+        let mut t_max = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+
+        for axis in 0..3 {
+            let last = self.size[axis] as i64 - 1;
+            cell[axis] = (start[axis].floor() as i64).clamp(0, last);
+
+            if ray_direction[axis] > 0.0 {
+                step[axis] = 1;
+                let boundary = self.origin[axis] + (cell[axis] + 1) as f32;
+                t_max[axis] = (boundary - ray_origin[axis]) * inv_dir[axis];
+                t_delta[axis] = inv_dir[axis];
+            } else if ray_direction[axis] < 0.0 {
+                step[axis] = -1;
+                let boundary = self.origin[axis] + cell[axis] as f32;
+                t_max[axis] = (boundary - ray_origin[axis]) * inv_dir[axis];
+                t_delta[axis] = -inv_dir[axis];
+            }
+        }
+
+        loop {
+            let block = self.get(cell[0] as usize, cell[1] as usize, cell[2] as usize);
+
+            // Si el rayo nace dentro de un bloque sólido lo ignoramos, igual que un rayo que sale
+            if block != AIR {
+                if let Some(axis) = hit_axis {
+                    let mut normal = Vec3::new(0.0, 0.0, 0.0);
+                    normal[axis] = -step[axis] as f32;
+
+                    let point = ray_origin + ray_direction * t;
+                    let cell_min =
+                        self.origin + Vec3::new(cell[0] as f32, cell[1] as f32, cell[2] as f32);
+                    return face_intersect(
+                        point,
+                        normal,
+                        t,
+                        &cell_min,
+                        &Vec3::new(1.0, 1.0, 1.0),
+                        self.material(block),
+                    );
+                }
+            }
+
+            let axis = if t_max[0] < t_max[1] && t_max[0] < t_max[2] {
+                0
+            } else if t_max[1] < t_max[2] {
+                1
+            } else {
+                2
+            };
+
+            t = t_max[axis];
+            cell[axis] += step[axis];
+            if cell[axis] < 0 || cell[axis] >= self.size[axis] as i64 {
+                return Intersect::empty();
+            }
+            t_max[axis] += t_delta[axis];
+            hit_axis = Some(axis);
+        }
+    }
+
+    fn aabb(&self) -> Aabb {
+        Aabb::new(
+            self.origin,
+            self.origin
+                + Vec3::new(
+                    self.size[0] as f32,
+                    self.size[1] as f32,
+                    self.size[2] as f32,
+                ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    fn unit_cube(x: f32, material: &Material) -> Cube {
+        Cube {
+            min: Vec3::new(x, 0.0, 0.0),
+            max: Vec3::new(x + 1.0, 1.0, 1.0),
+            material: material.clone(),
+        }
+    }
+
+    #[test]
+    fn blocks_share_ids_only_with_equal_materials() {
+        let stone = Material::new(Color::new(0.5, 0.5, 0.5), 0.9, 0.0, 1.5, Color::black());
+        let lamp = Material {
+            emission: Color::new(1.0, 0.8, 0.5),
+            ..stone.clone()
+        };
+        let grid = VoxelGrid::from_cubes(&[
+            unit_cube(0.0, &stone),
+            unit_cube(1.0, &stone),
+            unit_cube(2.0, &lamp),
+        ]);
+
+        assert_eq!(grid.get(0, 0, 0), grid.get(1, 0, 0));
+        assert_ne!(grid.get(0, 0, 0), grid.get(2, 0, 0));
+        assert_eq!(grid.material(grid.get(2, 0, 0)).emission, lamp.emission);
+    }
+}
@@ -0,0 +1,568 @@
+use crate::aabb::Aabb;
+use crate::cube::{face_intersect, Cube};
+use crate::material::Material;
+use crate::ray_intersect::{Intersect, RayIntersect};
+use crate::voxel::{BlockId, AIR};
+use nalgebra_glm::Vec3;
+use std::collections::HashMap;
+
+pub const CHUNK_SIZE: i32 = 16;
+// Sub-bloques de 4x4x4 dentro de cada chunk, uno por bit de `brick_mask`
+const BRICK_SIZE: i32 = 4;
+const BRICKS_PER_AXIS: i32 = CHUNK_SIZE / BRICK_SIZE;
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+pub type ChunkPos = (i32, i32, i32);
+
+pub struct Chunk {
+    blocks: Box<[BlockId; CHUNK_VOLUME]>,
+    occupancy: [u64; CHUNK_VOLUME / 64],
+    brick_mask: u64,
+    block_count: usize,
+}
+
+impl Chunk {
+    fn new() -> Self {
+        Chunk {
+            blocks: Box::new([AIR; CHUNK_VOLUME]),
+            occupancy: [0; CHUNK_VOLUME / 64],
+            brick_mask: 0,
+            block_count: 0,
+        }
+    }
+
+    fn index(x: i32, y: i32, z: i32) -> usize {
+        ((z * CHUNK_SIZE + y) * CHUNK_SIZE + x) as usize
+    }
+
+    fn brick_index(x: i32, y: i32, z: i32) -> u32 {
+        ((z * BRICKS_PER_AXIS + y) * BRICKS_PER_AXIS + x) as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.block_count == 0
+    }
+
+    pub fn get(&self, x: i32, y: i32, z: i32) -> BlockId {
+        self.blocks[Chunk::index(x, y, z)]
+    }
+
+    fn is_solid(&self, x: i32, y: i32, z: i32) -> bool {
+        let i = Chunk::index(x, y, z);
+        self.occupancy[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn is_brick_occupied(&self, x: i32, y: i32, z: i32) -> bool {
+        self.brick_mask & (1 << Chunk::brick_index(x, y, z)) != 0
+    }
+
+    fn set(&mut self, x: i32, y: i32, z: i32, block: BlockId) {
+        let i = Chunk::index(x, y, z);
+        let was_solid = self.blocks[i] != AIR;
+        self.blocks[i] = block;
+
+        if block != AIR {
+            self.occupancy[i / 64] |= 1 << (i % 64);
+            if !was_solid {
+                self.block_count += 1;
+            }
+        } else {
+            self.occupancy[i / 64] &= !(1 << (i % 64));
+            if was_solid {
+                self.block_count -= 1;
+            }
+        }
+
+        self.update_brick(x / BRICK_SIZE, y / BRICK_SIZE, z / BRICK_SIZE);
+    }
+
+    fn update_brick(&mut self, bx: i32, by: i32, bz: i32) {
+        let mut occupied = false;
+        'search: for z in bz * BRICK_SIZE..(bz + 1) * BRICK_SIZE {
+            for y in by * BRICK_SIZE..(by + 1) * BRICK_SIZE {
+                for x in bx * BRICK_SIZE..(bx + 1) * BRICK_SIZE {
+                    if self.is_solid(x, y, z) {
+                        occupied = true;
+                        break 'search;
+                    }
+                }
+            }
+        }
+
+        let bit = 1 << Chunk::brick_index(bx, by, bz);
+        if occupied {
+            self.brick_mask |= bit;
+        } else {
+            self.brick_mask &= !bit;
+        }
+    }
+}
+
+// Mundo disperso: solo existen los chunks de 16x16x16 que contienen algún bloque.
+pub struct World {
+    chunks: HashMap<ChunkPos, Chunk>,
+    palette: Vec<Material>,
+    chunk_min: [i32; 3],
+    chunk_max: [i32; 3],
+}
+
+impl World {
+    pub fn new() -> Self {
+        World {
+            chunks: HashMap::new(),
+            palette: vec![Material::black()],
+            chunk_min: [0; 3],
+            chunk_max: [-1; 3],
+        }
+    }
+
+    pub fn register_block(&mut self, material: Material) -> BlockId {
+        let block = BlockId::try_from(self.palette.len()).expect("too many block types");
+        self.palette.push(material);
+        block
+    }
+
+    pub fn material(&self, block: BlockId) -> &Material {
+        &self.palette[block as usize]
+    }
+
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> BlockId {
+        let (pos, local) = split_coords(x, y, z);
+        self.chunks
+            .get(&pos)
+            .map_or(AIR, |chunk| chunk.get(local[0], local[1], local[2]))
+    }
+
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: BlockId) {
+        let (pos, local) = split_coords(x, y, z);
+
+        if block == AIR {
+            if let Some(chunk) = self.chunks.get_mut(&pos) {
+                chunk.set(local[0], local[1], local[2], AIR);
+                if chunk.is_empty() {
+                    self.chunks.remove(&pos);
+                    self.update_bounds();
+                }
+            }
+            return;
+        }
+
+        let created = !self.chunks.contains_key(&pos);
+        self.chunks
+            .entry(pos)
+            .or_insert_with(Chunk::new)
+            .set(local[0], local[1], local[2], block);
+        if created {
+            self.update_bounds();
+        }
+    }
+
+    // Rellena la caja [min, max) con el mismo bloque
+    pub fn fill(&mut self, min: [i32; 3], max: [i32; 3], block: BlockId) {
+        for x in min[0]..max[0] {
+            for y in min[1]..max[1] {
+                for z in min[2]..max[2] {
+                    self.set_block(x, y, z, block);
+                }
+            }
+        }
+    }
+
+    // Un `Cube` por bloque, para comparar con la lista de objetos
+    pub fn to_cubes(&self) -> Vec<Cube> {
+        self.cubes_where(|_| true)
+    }
+
+    // Un `Cube` por cada bloque cuyo material cumple `keep`
+    pub fn cubes_where(&self, keep: impl Fn(&Material) -> bool) -> Vec<Cube> {
+        let mut cubes = Vec::new();
+        for (&(cx, cy, cz), chunk) in &self.chunks {
+            for z in 0..CHUNK_SIZE {
+                for y in 0..CHUNK_SIZE {
+                    for x in 0..CHUNK_SIZE {
+                        let block = chunk.get(x, y, z);
+                        if block == AIR || !keep(self.material(block)) {
+                            continue;
+                        }
+                        let min = Vec3::new(
+                            (cx * CHUNK_SIZE + x) as f32,
+                            (cy * CHUNK_SIZE + y) as f32,
+                            (cz * CHUNK_SIZE + z) as f32,
+                        );
+                        cubes.push(Cube {
+                            min,
+                            max: min + Vec3::new(1.0, 1.0, 1.0),
+                            material: self.material(block).clone(),
+                        });
+                    }
+                }
+            }
+        }
+        cubes
+    }
+
+    fn update_bounds(&mut self) {
+        self.chunk_min = [i32::MAX; 3];
+        self.chunk_max = [i32::MIN; 3];
+        for &(x, y, z) in self.chunks.keys() {
+            for (axis, c) in [x, y, z].into_iter().enumerate() {
+                self.chunk_min[axis] = self.chunk_min[axis].min(c);
+                self.chunk_max[axis] = self.chunk_max[axis].max(c);
+            }
+        }
+        if self.chunks.is_empty() {
+            self.chunk_min = [0; 3];
+            self.chunk_max = [-1; 3];
+        }
+    }
+
+    // Sigue el rayo mientras atraviesa bloques iguales a `block`; los bloques vecinos del mismo
+    // tipo forman un solo volumen, como el agua de un estanque. Si del otro lado hay otro bloque
+    // (el fondo del estanque), el rayo choca con él en lugar de salir.
+    fn exit_block(&self, ray: &Ray, block: BlockId) -> Intersect {
+        let mut cells = Dda::new(ray, 0.0, 1, [i32::MIN; 3], [i32::MAX; 3]);
+        loop {
+            let (axis, t_exit) = cells.next_boundary();
+            if !t_exit.is_finite() {
+                return Intersect::empty();
+            }
+            let cell = cells.cell;
+            cells.advance(axis);
+            let [x, y, z] = cells.cell;
+            let neighbor = self.get_block(x, y, z);
+            if neighbor == block {
+                continue;
+            }
+
+            let (cell, hit_block, side) = if neighbor == AIR {
+                (cell, block, 1)
+            } else {
+                (cells.cell, neighbor, -1)
+            };
+            let mut normal = Vec3::new(0.0, 0.0, 0.0);
+            normal[axis] = (side * cells.step[axis]) as f32;
+            let point = ray.origin + ray.direction * t_exit;
+            let cell_min = Vec3::new(cell[0] as f32, cell[1] as f32, cell[2] as f32);
+            return face_intersect(
+                point,
+                normal,
+                t_exit,
+                &cell_min,
+                &Vec3::new(1.0, 1.0, 1.0),
+                self.material(hit_block),
+            )
+            .with_medium(self.material(block).transmittance(t_exit));
+        }
+    }
+
+    // DDA jerárquico: chunk -> sub-bloque 4x4x4 -> bloque. Los chunks ausentes y los
+    // sub-bloques vacíos se saltan completos.
+    fn trace_chunk(
+        &self,
+        chunk_pos: ChunkPos,
+        chunk: &Chunk,
+        ray: &Ray,
+        t_start: f32,
+        t_end: f32,
+        mut entry_axis: Option<usize>,
+    ) -> Option<(f32, [i32; 3], BlockId, usize)> {
+        let (brick_min, brick_max) =
+            child_range([chunk_pos.0, chunk_pos.1, chunk_pos.2], BRICKS_PER_AXIS);
+        let mut bricks = Dda::new(ray, t_start, BRICK_SIZE, brick_min, brick_max);
+        let mut t = t_start;
+
+        while t < t_end {
+            let (brick_exit_axis, brick_exit) = bricks.next_boundary();
+            let local = bricks.cell.map(|c| c.rem_euclid(BRICKS_PER_AXIS));
+
+            if chunk.is_brick_occupied(local[0], local[1], local[2]) {
+                let (voxel_min, voxel_max) = child_range(bricks.cell, BRICK_SIZE);
+                let mut voxels = Dda::new(ray, t, 1, voxel_min, voxel_max);
+                let brick_end = brick_exit.min(t_end);
+                let mut tv = t;
+
+                while tv < brick_end {
+                    let cell = voxels.cell;
+                    let [lx, ly, lz] = cell.map(|c| c.rem_euclid(CHUNK_SIZE));
+
+                    if chunk.is_solid(lx, ly, lz) {
+                        // Si el rayo nace dentro de un bloque sólido lo ignoramos
+                        if let Some(axis) = entry_axis {
+                            return Some((tv, cell, chunk.get(lx, ly, lz), axis));
+                        }
+                    }
+
+                    let (axis, t_next) = voxels.next_boundary();
+                    voxels.advance(axis);
+                    tv = t_next;
+                    entry_axis = Some(axis);
+                    if !voxels.inside(voxel_min, voxel_max) {
+                        break;
+                    }
+                }
+            }
+
+            bricks.advance(brick_exit_axis);
+            t = brick_exit;
+            entry_axis = Some(brick_exit_axis);
+            if !bricks.inside(brick_min, brick_max) {
+                break;
+            }
+        }
+
+        None
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World::new()
+    }
+}
+
+fn split_coords(x: i32, y: i32, z: i32) -> (ChunkPos, [i32; 3]) {
+    (
+        (
+            x.div_euclid(CHUNK_SIZE),
+            y.div_euclid(CHUNK_SIZE),
+            z.div_euclid(CHUNK_SIZE),
+        ),
+        [
+            x.rem_euclid(CHUNK_SIZE),
+            y.rem_euclid(CHUNK_SIZE),
+            z.rem_euclid(CHUNK_SIZE),
+        ],
+    )
+}
+
+struct Ray {
+    origin: Vec3,
+    direction: Vec3,
+    inv_dir: Vec3,
+}
+
+// Estado de un recorrido Amanatides–Woo sobre celdas de `cell_size` unidades
+struct Dda {
+    cell: [i32; 3],
+    step: [i32; 3],
+    t_max: [f32; 3],
+    t_delta: [f32; 3],
+}
+
+impl Dda {
+    // La celda inicial se acota a [min_cell, max_cell] (las hijas de la celda padre), lo que
+    // evita errores de redondeo justo sobre los bordes.
+    fn new(ray: &Ray, t: f32, cell_size: i32, min_cell: [i32; 3], max_cell: [i32; 3]) -> Self {
+        let point = ray.origin + ray.direction * t;
+        let size = cell_size as f32;
+        let mut dda = Dda {
+            cell: [0; 3],
+            step: [0; 3],
+            t_max: [f32::INFINITY; 3],
+            t_delta: [f32::INFINITY; 3],
+        };
+
+        for axis in 0..3 {
+            let cell = ((point[axis] / size).floor() as i32).clamp(min_cell[axis], max_cell[axis]);
+            dda.cell[axis] = cell;
+
+            if ray.direction[axis] > 0.0 {
+                dda.step[axis] = 1;
+                let boundary = (cell + 1) as f32 * size;
+                dda.t_max[axis] = (boundary - ray.origin[axis]) * ray.inv_dir[axis];
+                dda.t_delta[axis] = size * ray.inv_dir[axis];
+            } else if ray.direction[axis] < 0.0 {
+                dda.step[axis] = -1;
+                let boundary = cell as f32 * size;
+                dda.t_max[axis] = (boundary - ray.origin[axis]) * ray.inv_dir[axis];
+                dda.t_delta[axis] = -size * ray.inv_dir[axis];
+            }
+        }
+
+        dda
+    }
+
+    fn next_boundary(&self) -> (usize, f32) {
+        let axis = if self.t_max[0] < self.t_max[1] && self.t_max[0] < self.t_max[2] {
+            0
+        } else if self.t_max[1] < self.t_max[2] {
+            1
+        } else {
+            2
+        };
+        (axis, self.t_max[axis])
+    }
+
+    fn advance(&mut self, axis: usize) {
+        self.cell[axis] += self.step[axis];
+        self.t_max[axis] += self.t_delta[axis];
+    }
+
+    fn inside(&self, min_cell: [i32; 3], max_cell: [i32; 3]) -> bool {
+        (0..3).all(|axis| self.cell[axis] >= min_cell[axis] && self.cell[axis] <= max_cell[axis])
+    }
+}
+
+// Rango de celdas hijas de `parent` cuando cada celda padre contiene `per_parent` por eje
+fn child_range(parent: [i32; 3], per_parent: i32) -> ([i32; 3], [i32; 3]) {
+    let min = parent.map(|c| c * per_parent);
+    (min, min.map(|c| c + per_parent - 1))
+}
+
+impl RayIntersect for World {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        if self.chunks.is_empty() {
+            return Intersect::empty();
+        }
+
+        let ray = Ray {
+            origin: *ray_origin,
+            direction: *ray_direction,
+            inv_dir: Vec3::new(
+                1.0 / ray_direction.x,
+                1.0 / ray_direction.y,
+                1.0 / ray_direction.z,
+            ),
+        };
+
+        // Un rayo que nace dentro de un bloque transparente (vidrio, agua) choca con la cara por
+        // la que sale
+        let start = ray_origin.map(|c| c.floor() as i32);
+        let start_block = self.get_block(start.x, start.y, start.z);
+        if start_block != AIR && self.material(start_block).is_transparent() {
+            return self.exit_block(&ray, start_block);
+        }
+
+        let bounds = self.aabb();
+        let Some(t_enter) = bounds.hit(ray_origin, &ray.inv_dir, f32::INFINITY) else {
+            return Intersect::empty();
+        };
+
+        let mut entry_axis = None;
+        if t_enter > 0.0 {
+            let near = (bounds.min - ray_origin)
+                .component_mul(&ray.inv_dir)
+                .inf(&(bounds.max - ray_origin).component_mul(&ray.inv_dir));
+            entry_axis = Some(if near.x >= near.y && near.x >= near.z {
+                0
+            } else if near.y >= near.z {
+                1
+            } else {
+                2
+            });
+        }
+
+        let mut t = t_enter.max(0.0);
+        let mut chunks = Dda::new(&ray, t, CHUNK_SIZE, self.chunk_min, self.chunk_max);
+
+        loop {
+            let (exit_axis, t_exit) = chunks.next_boundary();
+            let pos = (chunks.cell[0], chunks.cell[1], chunks.cell[2]);
+
+            if let Some(chunk) = self.chunks.get(&pos) {
+                if let Some((t_hit, cell, block, axis)) =
+                    self.trace_chunk(pos, chunk, &ray, t, t_exit, entry_axis)
+                {
+                    let mut normal = Vec3::new(0.0, 0.0, 0.0);
+                    normal[axis] = -chunks.step[axis] as f32;
+
+                    let point = ray_origin + ray_direction * t_hit;
+                    let cell_min = Vec3::new(cell[0] as f32, cell[1] as f32, cell[2] as f32);
+                    return face_intersect(
+                        point,
+                        normal,
+                        t_hit,
+                        &cell_min,
+                        &Vec3::new(1.0, 1.0, 1.0),
+                        self.material(block),
+                    );
+                }
+            }
+
+            chunks.advance(exit_axis);
+            t = t_exit;
+            entry_axis = Some(exit_axis);
+
+            if !chunks.inside(self.chunk_min, self.chunk_max) {
+                break;
+            }
+        }
+
+        Intersect::empty()
+    }
+
+    fn aabb(&self) -> Aabb {
+        let size = CHUNK_SIZE as f32;
+        Aabb::new(
+            Vec3::new(
+                self.chunk_min[0] as f32 * size,
+                self.chunk_min[1] as f32 * size,
+                self.chunk_min[2] as f32 * size,
+            ),
+            Vec3::new(
+                (self.chunk_max[0] + 1) as f32 * size,
+                (self.chunk_max[1] + 1) as f32 * size,
+                (self.chunk_max[2] + 1) as f32 * size,
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn ray_inside_water_hits_the_floor() {
+        let mut world = World::new();
+        let dirt = world.register_block(Material::new(
+            Color::new(0.4, 0.3, 0.2),
+            1.0,
+            0.0,
+            1.5,
+            Color::black(),
+        ));
+        let water = world.register_block(
+            Material::new(Color::new(0.2, 0.4, 0.8), 0.0, 0.0, 1.33, Color::black())
+                .with_transmission(1.0)
+                .with_absorption(Color::new(0.6, 0.2, 0.1)),
+        );
+        world.fill([0, 0, 0], [3, 1, 3], dirt);
+        world.fill([0, 1, 0], [3, 3, 3], water);
+
+        let origin = Vec3::new(1.5, 2.5, 1.5);
+        let direction = Vec3::new(0.3, -1.0, 0.2).normalize();
+        let hit = world.ray_intersect(&origin, &direction);
+
+        assert!(hit.is_intersecting);
+        assert!((hit.point.y - 1.0).abs() < 1e-4, "hit at {:?}", hit.point);
+        assert_eq!(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!(!hit.material.is_transparent());
+
+        // El fondo mira hacia el rayo, pero la luz igual cruzó 1.5 bloques de agua en vertical
+        let distance = 1.5 / direction.y.abs();
+        assert!((hit.distance - distance).abs() < 1e-4);
+        let transmittance = hit.transmittance(&direction);
+        assert!((transmittance.r - (-0.6 * distance).exp()).abs() < 1e-4);
+        assert!((transmittance.g - (-0.2 * distance).exp()).abs() < 1e-4);
+        assert!((transmittance.b - (-0.1 * distance).exp()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn ray_inside_water_exits_to_air() {
+        let mut world = World::new();
+        let water = world.register_block(
+            Material::new(Color::new(0.2, 0.4, 0.8), 0.0, 0.0, 1.33, Color::black())
+                .with_transmission(1.0),
+        );
+        world.fill([0, 0, 0], [2, 2, 2], water);
+
+        let hit = world.ray_intersect(&Vec3::new(0.5, 0.5, 0.5), &Vec3::new(0.0, 1.0, 0.0));
+
+        assert!(hit.is_intersecting);
+        assert!((hit.distance - 1.5).abs() < 1e-4);
+        assert_eq!(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!(hit.material.is_transparent());
+    }
+}